@@ -7,6 +7,11 @@ fn main() {
         Ok(update_type) => match update_type {
             UpdateType::Success => println!("Success"),
             UpdateType::AlreadyInPath => println!("Already in Path"),
+            UpdateType::Moved => println!("Moved"),
+            UpdateType::Replaced => println!("Replaced"),
+            UpdateType::Removed => println!("Removed"),
+            UpdateType::NotInPath => println!("Not in Path"),
+            _ => println!("{update_type:?}"),
         },
         Err(e) => println!("Error: {e}"),
     }
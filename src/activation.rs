@@ -0,0 +1,34 @@
+/// A command activating the updated PATH environment variable in the current
+/// shell session
+///
+/// Updating the PATH only affects the shells started afterwards, so the
+/// current session must either run this command or be restarted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Activation {
+    /// The name of the shell running the command (e.g., `zsh` or
+    /// `PowerShell`).
+    pub shell: String,
+
+    /// The command activating the updated PATH, ready to be printed.
+    pub command: String,
+}
+
+impl Activation {
+    /// The default message template, used by [`Activation::message`]
+    pub const MESSAGE: &'static str =
+        "To update the PATH of the current {shell} session, run `{command}` or restart your shell.";
+
+    /// Formats the default message telling the user how to activate the
+    /// updated PATH
+    pub fn message(&self) -> String {
+        self.format(Self::MESSAGE)
+    }
+
+    /// Formats the given message template, replacing `{shell}` and
+    /// `{command}` with the name of the shell and the command
+    pub fn format(&self, template: &str) -> String {
+        template
+            .replace("{shell}", &self.shell)
+            .replace("{command}", &self.command)
+    }
+}
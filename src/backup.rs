@@ -0,0 +1,193 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The marker separating the name of the backed up file from the timestamp
+/// of the backup
+const SUFFIX: &str = ".pathman-backup-";
+
+/// The extension of the backups recording that the file didn't exist, so
+/// that restoring them deletes the file
+const ABSENT: &str = ".absent";
+
+/// Settings for backing up the files before they are modified
+///
+/// By default, the backups are stored in the state directory of the user
+/// (`$XDG_STATE_HOME/pathman/backups`, which defaults to
+/// `~/.local/state/pathman/backups`, or `%LOCALAPPDATA%\pathman\backups` on
+/// Windows), where each file is backed up at its own path (e.g.,
+/// `home/alice/.zshrc.pathman-backup-<timestamp>`), and the last 10 backups of
+/// each file are kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    pub(crate) dir: Option<PathBuf>,
+    pub(crate) next_to_file: bool,
+    pub(crate) outside_directory: bool,
+    pub(crate) top_directory: Option<PathBuf>,
+    pub(crate) keep: usize,
+}
+
+impl Default for Backup {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            next_to_file: false,
+            outside_directory: false,
+            top_directory: None,
+            keep: 10,
+        }
+    }
+}
+
+impl Backup {
+    /// Creates the default backup settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the backups in the given directory, instead of the state
+    /// directory of the user
+    pub fn dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Stores the backups next to the backed up files (e.g.,
+    /// `~/.zshrc.pathman-backup-<timestamp>`)
+    ///
+    /// The files of directories whose every file is read, such as
+    /// `/etc/paths.d` or the skeleton directory, are backed up in a sibling
+    /// directory instead (e.g.,
+    /// `/etc/paths.d.pathman-backups/tool.pathman-backup-<timestamp>`, or
+    /// `/etc/skel.pathman-backups/.config/fish/config.fish.pathman-backup-<timestamp>`
+    /// for the files in the subdirectories of the skeleton directory), so that
+    /// the backups aren't read along with them.
+    ///
+    /// This option has no effect on Windows, where the registry is backed up.
+    pub fn next_to_file(mut self, next_to_file: bool) -> Self {
+        self.next_to_file = next_to_file;
+        self
+    }
+
+    /// Sets the number of backups kept for each file, the older ones being
+    /// deleted when a new backup is made
+    ///
+    /// At least the last backup is kept, so that the last change can always
+    /// be rolled back.
+    pub fn keep(mut self, keep: usize) -> Self {
+        self.keep = keep.max(1);
+        self
+    }
+
+    /// Stores the backups made next to the files in a sibling directory of
+    /// their directory
+    #[cfg(unix)]
+    pub(crate) fn outside_directory(mut self, outside_directory: bool) -> Self {
+        self.outside_directory = outside_directory;
+        self
+    }
+
+    /// Sets the directory whose whole tree is read, so that the backups of
+    /// the files in its subdirectories are stored outside of it as well
+    ///
+    /// The directory of each file is used otherwise.
+    #[cfg(unix)]
+    pub(crate) fn top_directory<P: AsRef<Path>>(mut self, top_directory: P) -> Self {
+        self.top_directory = Some(top_directory.as_ref().to_path_buf());
+        self
+    }
+
+    /// Returns the path of the backups of the given file, without their
+    /// suffix
+    fn base(&self, file: &Path) -> PathBuf {
+        match (&self.dir, self.next_to_file) {
+            (_, true) if self.outside_directory => {
+                let top = match (&self.top_directory, file.parent()) {
+                    (Some(top), _) if file.starts_with(top) => top.as_path(),
+                    (_, Some(parent)) => parent,
+                    (_, None) => return file.to_path_buf(),
+                };
+                let relative = file.strip_prefix(top).unwrap_or(file);
+
+                let mut dir = top.as_os_str().to_os_string();
+                dir.push(".pathman-backups");
+
+                PathBuf::from(dir).join(relative)
+            }
+            (Some(dir), false) => {
+                // Mirror the location of the file in the backup directory, so
+                // that files of the same name don't clash
+                let relative: PathBuf = file
+                    .components()
+                    .filter(|c| matches!(c, std::path::Component::Normal(_)))
+                    .collect();
+
+                dir.join(relative)
+            }
+            _ => file.to_path_buf(),
+        }
+    }
+
+    /// Returns the path of a new backup of the given file
+    ///
+    /// The timestamp has a fixed width, so that the backups sort in
+    /// chronological order.
+    pub(crate) fn create_path(&self, file: &Path) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+
+        let mut base = self.base(file).into_os_string();
+        base.push(format!("{SUFFIX}{timestamp:020}"));
+
+        PathBuf::from(base)
+    }
+
+    /// Returns the path of a new backup recording that the given file
+    /// doesn't exist
+    pub(crate) fn create_absent_path(&self, file: &Path) -> PathBuf {
+        let mut path = self.create_path(file).into_os_string();
+        path.push(ABSENT);
+
+        PathBuf::from(path)
+    }
+
+    /// Returns whether the given backup records that its file didn't exist
+    pub(crate) fn is_absent(backup: &Path) -> bool {
+        backup.to_string_lossy().ends_with(ABSENT)
+    }
+
+    /// Returns the existing backups of the given file, from the oldest to the
+    /// newest
+    pub(crate) fn list(&self, file: &Path) -> Vec<PathBuf> {
+        let base = self.base(file);
+        let (Some(dir), Some(name)) = (base.parent(), base.file_name()) else {
+            return Vec::new();
+        };
+        let prefix = format!("{}{SUFFIX}", name.to_string_lossy());
+
+        let mut backups: Vec<PathBuf> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+                .map(|entry| entry.path())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        backups.sort();
+
+        backups
+    }
+
+    /// Deletes the oldest backups of the given file beyond the number of
+    /// backups to keep
+    ///
+    /// Failing to delete an old backup doesn't affect the update.
+    pub(crate) fn prune(&self, file: &Path) {
+        let backups = self.list(file);
+
+        for backup in &backups[..backups.len().saturating_sub(self.keep)] {
+            let _ = std::fs::remove_file(backup);
+        }
+    }
+}
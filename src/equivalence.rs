@@ -0,0 +1,147 @@
+use crate::expand::{Segment, parse};
+use home::home_dir;
+use std::env::var;
+use std::path::{Component, Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
+
+/// Rules for deciding whether two PATH entries refer to the same directory
+///
+/// Entries are always compared after lexical normalization, which removes
+/// trailing and repeated separators as well as `.` and `..` components, so
+/// that `/usr/local/bin/`, `/usr/local//bin` and `/usr/local/./bin` are all
+/// considered equivalent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Equivalence {
+    pub(crate) expand: bool,
+    pub(crate) canonicalize: bool,
+    pub(crate) case_insensitive: bool,
+}
+
+impl Default for Equivalence {
+    fn default() -> Self {
+        Self {
+            expand: true,
+            canonicalize: false,
+            case_insensitive: cfg!(any(windows, target_os = "macos")),
+        }
+    }
+}
+
+impl Equivalence {
+    /// Creates the default equivalence rules
+    ///
+    /// By default, `~` and environment variables are expanded, symbolic links
+    /// are not resolved, and entries are compared case-insensitively on
+    /// macOS and Windows only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expands `~` and environment variables before comparing entries
+    ///
+    /// When enabled, `$HOME/bin` and `/home/me/bin` are considered
+    /// equivalent. References to undefined variables are left untouched.
+    pub fn expand(mut self, expand: bool) -> Self {
+        self.expand = expand;
+        self
+    }
+
+    /// Resolves symbolic links before comparing entries
+    ///
+    /// This requires the directories to exist, and entries that cannot be
+    /// resolved are compared lexically.
+    pub fn canonicalize(mut self, canonicalize: bool) -> Self {
+        self.canonicalize = canonicalize;
+        self
+    }
+
+    /// Compares entries case-insensitively and regardless of their Unicode
+    /// normalization form
+    ///
+    /// This matches the behavior of the default filesystems of macOS and
+    /// Windows.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Determines whether the two PATH entries refer to the same directory
+    pub fn equivalent(&self, a: &str, b: &str) -> bool {
+        let home = home_dir();
+
+        self.normalize(a, home.as_deref()) == self.normalize(b, home.as_deref())
+    }
+
+    /// Normalizes the given PATH entry according to the rules, using the
+    /// given home directory to expand `~` and `$HOME`.
+    ///
+    /// Without a home directory, `~` and `$HOME` are both normalized to
+    /// `${HOME}`.
+    pub(crate) fn normalize(&self, entry: &str, home: Option<&Path>) -> String {
+        let home = match home {
+            Some(home) => home.to_string_lossy().to_string(),
+            None => "${HOME}".to_string(),
+        };
+
+        let mut entry = match self.expand {
+            true => parse(entry)
+                .into_iter()
+                .map(|segment| match segment {
+                    Segment::Literal(literal) => literal,
+                    Segment::Home => home.clone(),
+                    Segment::Variable(name) if name == "HOME" => home.clone(),
+                    Segment::Variable(name) => match var(&name) {
+                        Ok(value) => value,
+                        Err(_) => format!("${{{name}}}"),
+                    },
+                })
+                .collect(),
+            false => entry.to_string(),
+        };
+
+        if self.canonicalize
+            && let Ok(canonical) = std::fs::canonicalize(&entry)
+        {
+            entry = canonical.to_string_lossy().to_string();
+            // Canonical paths are verbatim paths on Windows
+            if let Some(stripped) = entry.strip_prefix(r"\\?\") {
+                entry = stripped.to_string();
+            }
+        }
+
+        let entry = normalize_lexically(&entry);
+
+        match self.case_insensitive {
+            true => entry.nfc().collect::<String>().to_lowercase(),
+            false => entry,
+        }
+    }
+}
+
+/// Normalizes the given path without accessing the filesystem
+fn normalize_lexically(entry: &str) -> String {
+    // Empty entries refer to the working directory
+    if entry.is_empty() {
+        return String::new();
+    }
+
+    let mut normalized = PathBuf::new();
+
+    for component in Path::new(entry).components() {
+        match component {
+            Component::CurDir => {}
+            // Only a directory name can be cancelled by `..`, which is kept at
+            // the beginning of relative paths and ignored after the root
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(component),
+            },
+            component => normalized.push(component),
+        }
+    }
+
+    normalized.to_string_lossy().to_string()
+}
@@ -30,6 +30,9 @@ pub enum PathmanError {
     #[error("Unable to create export line for the shell configuration file")]
     UnableToCreateExportCommand,
 
+    #[error("Undefined environment variable: {0}")]
+    UndefinedVariable(String),
+
     #[error("Unable to read the environment registry subkey")]
     UnableToOpenEnvironmentKey,
 
@@ -38,4 +41,25 @@ pub enum PathmanError {
     
     #[error("Unable to write the PATH environment variable to the registry")]
     UnableToWritePathEnvironmentKey,
+
+    #[error("Unsupported target on this platform")]
+    UnsupportedTarget,
+
+    #[error("Insufficient permissions to write the system-wide file: {0}")]
+    InsufficientPermissions(String),
+
+    #[error("Unable to find the user in the passwd database: {0}")]
+    UnknownUser(String),
+
+    #[error("The configuration file is managed declaratively and cannot be modified: {0}")]
+    ManagedConfigFile(String),
+
+    #[error("No backup found for the file: {0}")]
+    NoBackup(String),
+
+    #[error("Invalid environment variable name: {0}")]
+    InvalidVariableName(String),
+
+    #[error("Unable to remove the entry from a line not written by pathman: {0}")]
+    UnableToRemoveEntry(String),
 }
\ No newline at end of file
@@ -0,0 +1,169 @@
+use crate::error::PathmanError;
+use crate::error::PathmanError::{UnableToFindHomeDirectory, UndefinedVariable};
+use std::env::var;
+use std::path::Path;
+
+/// How `~` and environment variables in input paths are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Expansion {
+    /// Expands `~` and environment variables before updating the PATH.
+    ///
+    /// An error is returned if the path references an undefined variable.
+    #[default]
+    Immediate,
+
+    /// Preserves `~` and environment variables, so that they are expanded by
+    /// the shell (or by Windows) whenever the PATH is read.
+    Deferred,
+}
+
+/// A segment of an input path
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Segment {
+    /// Literal text
+    Literal(String),
+
+    /// A leading `~`, referring to the home directory
+    Home,
+
+    /// A reference to an environment variable (`$VAR`, `${VAR}`, or `%VAR%`
+    /// on Windows)
+    Variable(String),
+}
+
+/// Splits the given path into literal text, `~`, and variable references.
+pub(crate) fn parse(path: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+
+    let mut rest = path;
+
+    // A tilde is only meaningful at the beginning of the path
+    if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+        segments.push(Segment::Home);
+        rest = &rest[1..];
+    }
+
+    while let Some(c) = rest.chars().next() {
+        let variable = match c {
+            '$' => parse_dollar_variable(&rest[1..]),
+            '%' if cfg!(windows) => parse_percent_variable(&rest[1..]),
+            _ => None,
+        };
+
+        match variable {
+            Some((name, length)) => {
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Variable(name.to_string()));
+                rest = &rest[1 + length..];
+            }
+            None => {
+                literal.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+
+    segments
+}
+
+/// Parses the variable name following a `$`, returning the name and the
+/// number of bytes it spans (including braces).
+fn parse_dollar_variable(input: &str) -> Option<(&str, usize)> {
+    if let Some(braced) = input.strip_prefix('{') {
+        let end = braced.find('}')?;
+        let name = &braced[..end];
+
+        return is_variable_name(name).then_some((name, end + 2));
+    }
+
+    let end = input
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(input.len());
+    let name = &input[..end];
+
+    is_variable_name(name).then_some((name, end))
+}
+
+/// Parses the variable name following a `%`, returning the name and the
+/// number of bytes it spans (including the closing `%`).
+fn parse_percent_variable(input: &str) -> Option<(&str, usize)> {
+    let end = input.find('%')?;
+    let name = &input[..end];
+
+    (!name.is_empty() && !name.contains(['\\', '/', ';'])).then_some((name, end + 1))
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// Expands `~` and environment variables in the given path.
+///
+/// The `~` and `$HOME` are replaced with the given home directory, and an
+/// error is returned for variables that are not defined, or when the path
+/// refers to the home directory and none is given.
+pub(crate) fn expand(path: &str, home: Option<&Path>) -> Result<String, PathmanError> {
+    let mut expanded = String::new();
+
+    for segment in parse(path) {
+        match segment {
+            Segment::Literal(literal) => expanded.push_str(&literal),
+            Segment::Home => match home {
+                Some(home) => expanded.push_str(&home.to_string_lossy()),
+                None => return Err(UnableToFindHomeDirectory),
+            },
+            Segment::Variable(name) if name == "HOME" => match home {
+                Some(home) => expanded.push_str(&home.to_string_lossy()),
+                None => return Err(UnableToFindHomeDirectory),
+            },
+            Segment::Variable(name) => match var(&name) {
+                Ok(value) => expanded.push_str(&value),
+                Err(_) => return Err(UndefinedVariable(name)),
+            },
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Expands the environment variables in the given path, keeping a leading
+/// `~` or `$HOME` as `~` so that the home directory is left to the reader.
+///
+/// An error is returned for variables that are not defined, or when the
+/// home directory is referenced elsewhere than at the beginning of the path.
+#[cfg(unix)]
+pub(crate) fn expand_variables(path: &str) -> Result<String, PathmanError> {
+    let mut expanded = String::new();
+
+    for (index, segment) in parse(path).into_iter().enumerate() {
+        match segment {
+            Segment::Literal(literal) if expanded == "~" && !literal.starts_with('/') => {
+                return Err(UnableToFindHomeDirectory);
+            }
+            Segment::Literal(literal) => expanded.push_str(&literal),
+            Segment::Home => expanded.push('~'),
+            Segment::Variable(name) if name == "HOME" && index == 0 => expanded.push('~'),
+            Segment::Variable(name) if name == "HOME" => return Err(UnableToFindHomeDirectory),
+            Segment::Variable(name) => match var(&name) {
+                Ok(value) => expanded.push_str(&value),
+                Err(_) => return Err(UndefinedVariable(name)),
+            },
+        }
+    }
+
+    Ok(expanded)
+}
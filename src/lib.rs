@@ -1,12 +1,36 @@
 use crate::platform::PathUpdater;
 use crate::platform::PlatformPathUpdater;
-use std::path::Path;
+use home::home_dir;
+use std::env::{split_paths, var_os};
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
+mod activation;
+mod backup;
+mod equivalence;
 mod error;
+mod expand;
+mod options;
+mod path_helper;
+mod path_list;
 mod platform;
+mod process;
+mod status;
+mod target;
+mod user;
 
+pub use activation::Activation;
+pub use backup::Backup;
+pub use equivalence::Equivalence;
 pub use error::PathmanError;
-pub use platform::UpdateType;
+pub use expand::Expansion;
+pub use options::UpdateOptions;
+pub use path_helper::PathHelper;
+pub use path_list::PathList;
+pub use platform::{Fallback, Position, UpdateType, WriteStrategy, WrittenFile};
+pub use status::{Location, PathStatus};
+pub use target::Target;
+pub use user::User;
 
 /// Prepends the given path to the PATH environment variable
 ///
@@ -34,7 +58,26 @@ pub fn prepend_to_path<P: AsRef<Path>>(
     path: P,
     comment: Option<&str>,
 ) -> Result<UpdateType, PathmanError> {
-    PlatformPathUpdater::prepend(path, comment)
+    prepend_to_path_with_options(path, comment, &UpdateOptions::default())
+}
+
+/// Prepends the given path to the PATH environment variable using the given
+/// options
+///
+/// This function behaves like [`prepend_to_path`], but allows customizing the
+/// update through [`UpdateOptions`].
+pub fn prepend_to_path_with_options<P: AsRef<Path>>(
+    path: P,
+    comment: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<UpdateType, PathmanError> {
+    check_name(&options.variable)?;
+    let update_type = PlatformPathUpdater::prepend(&path, comment, options)?;
+    process::update_process(options, |current| {
+        process::add_paths(current, &[path.as_ref()], &Position::Front, options)
+    })?;
+
+    Ok(update_type)
 }
 
 /// Appends the given path to the PATH environment variable
@@ -63,5 +106,421 @@ pub fn append_to_path<P: AsRef<Path>>(
     path: P,
     comment: Option<&str>,
 ) -> Result<UpdateType, PathmanError> {
-    PlatformPathUpdater::append(path, comment)
+    append_to_path_with_options(path, comment, &UpdateOptions::default())
+}
+
+/// Appends the given path to the PATH environment variable using the given
+/// options
+///
+/// This function behaves like [`append_to_path`], but allows customizing the
+/// update through [`UpdateOptions`].
+pub fn append_to_path_with_options<P: AsRef<Path>>(
+    path: P,
+    comment: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<UpdateType, PathmanError> {
+    check_name(&options.variable)?;
+    let update_type = PlatformPathUpdater::append(&path, comment, options)?;
+    process::update_process(options, |current| {
+        process::add_paths(current, &[path.as_ref()], &Position::Back, options)
+    })?;
+
+    Ok(update_type)
+}
+
+/// Adds the given path to the PATH environment variable at the given position
+///
+/// In addition to the beginning and the end of the PATH, the path can be
+/// added directly before or after another entry of the PATH, called the
+/// anchor, which makes it possible to shadow a system directory without
+/// overriding the user's own directories. When the anchor is not in the
+/// PATH, the path is added at the fallback position instead.
+///
+/// # OS-specific behavior
+///
+/// # macOS and Linux
+///
+/// On macOS and Linux, the line added to the shell configuration file splices
+/// the path into the PATH when the shell starts, depending on whether the
+/// anchor is present at that time.
+///
+/// # Windows
+///
+/// On Windows, the path is spliced into the user's PATH in the registry.
+pub fn add_to_path<P: AsRef<Path>>(
+    path: P,
+    position: &Position,
+    comment: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<UpdateType, PathmanError> {
+    check_name(&options.variable)?;
+    let update_type = PlatformPathUpdater::insert(&path, position, comment, options)?;
+    process::update_process(options, |current| {
+        process::add_paths(current, &[path.as_ref()], position, options)
+    })?;
+
+    Ok(update_type)
+}
+
+/// Adds the given paths to the PATH environment variable at the given position
+///
+/// This function is useful when a tool installs several directories, as they
+/// are added in a single update, keeping their order. The paths that are
+/// already in the PATH are left untouched, and the update type of each path
+/// is returned along with it, in the order of the input.
+///
+/// # OS-specific behavior
+///
+/// # macOS and Linux
+///
+/// On macOS and Linux, a single line adding all the new paths is written to
+/// the shell configuration file, preceded by the comment. When
+/// [`UpdateOptions::only_if_exists`] is set, each path gets its own line,
+/// since the existence of each directory is checked separately.
+///
+/// # Windows
+///
+/// On Windows, the user's PATH is written to the registry once.
+pub fn add_all_to_path<P: AsRef<Path>>(
+    paths: &[P],
+    position: &Position,
+    comment: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<Vec<(PathBuf, UpdateType)>, PathmanError> {
+    check_name(&options.variable)?;
+    let update_types = PlatformPathUpdater::insert_all(paths, position, comment, options)?;
+    process::update_process(options, |current| {
+        let paths: Vec<&Path> = paths.iter().map(AsRef::as_ref).collect();
+        process::add_paths(current, &paths, position, options)
+    })?;
+
+    Ok(paths
+        .iter()
+        .map(|path| path.as_ref().to_path_buf())
+        .zip(update_types)
+        .collect())
+}
+
+/// Replaces a path with another one in the PATH environment variable
+///
+/// This function is useful when a tool moves to a different directory, as it
+/// swaps the old directory for the new one in a single operation, keeping the
+/// position of the old directory in the PATH. When the old path is not in the
+/// PATH, the new path is prepended to it instead, as with
+/// [`prepend_to_path_with_options`].
+///
+/// # OS-specific behavior
+///
+/// # macOS and Linux
+///
+/// On macOS and Linux, the old path is replaced in the lines of the shell
+/// configuration file adding it to the PATH, preserving their comments.
+///
+/// # Windows
+///
+/// On Windows, the old path is replaced in the user's PATH in the registry.
+pub fn replace_in_path<O: AsRef<Path>, N: AsRef<Path>>(
+    old: O,
+    new: N,
+    options: &UpdateOptions,
+) -> Result<UpdateType, PathmanError> {
+    check_name(&options.variable)?;
+    let update_type = PlatformPathUpdater::replace(&old, &new, options)?;
+    process::update_process(options, |current| {
+        process::replace_path(current, old.as_ref(), new.as_ref(), options)
+    })?;
+
+    Ok(update_type)
+}
+
+/// Removes the given path from the PATH environment variable
+///
+/// Paths are compared according to the equivalence rules of the options, and
+/// [`UpdateType::NotInPath`] is returned if the path is not in the PATH.
+///
+/// # OS-specific behavior
+///
+/// # macOS and Linux
+///
+/// On macOS and Linux, the lines of the shell configuration file adding the
+/// path to the PATH are removed, along with the preceding comment if it is
+/// the given comment, as written when the path was added. Lines adding other
+/// paths as well, and lines that were not written by this crate (e.g., in an
+/// `if` block), are kept without the removed path, and
+/// [`PathmanError::UnableToRemoveEntry`] is returned if the variable would be
+/// left empty.
+///
+/// # Windows
+///
+/// On Windows, the path is removed from the user's PATH in the registry.
+pub fn remove_from_path<P: AsRef<Path>>(
+    path: P,
+    comment: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<UpdateType, PathmanError> {
+    check_name(&options.variable)?;
+    let update_type = PlatformPathUpdater::remove(&path, comment, options)?;
+    process::update_process(options, |current| {
+        process::remove_path(current, path.as_ref(), options)
+    })?;
+
+    Ok(update_type)
+}
+
+/// Sets the given environment variable to the given value persistently
+///
+/// This function is useful for variables such as `JAVA_HOME` that tools rely
+/// on alongside the PATH. When the variable is already set to an equivalent
+/// value, [`UpdateType::AlreadyInPath`] is returned, and when it is set to a
+/// different value, the value is updated in place and
+/// [`UpdateType::Replaced`] is returned.
+///
+/// The name must only contain ASCII letters, digits and underscores, and
+/// must not start with a digit, otherwise
+/// [`PathmanError::InvalidVariableName`] is returned.
+///
+/// The value is handled like a path: `~` and environment variables are
+/// expanded according to [`UpdateOptions::expansion`], and values located in
+/// the home directory are written relative to it on macOS and Linux. Values
+/// that are not paths can be written as is with [`UpdateOptions::literal`].
+///
+/// # OS-specific behavior
+///
+/// # macOS and Linux
+///
+/// On macOS and Linux, a command setting the variable (e.g.,
+/// `export NAME="value"` or `set -gx NAME "value"`) is added to the user's
+/// shell configuration file.
+///
+/// # Windows
+///
+/// On Windows, the variable is set in the user's environment in the registry.
+pub fn set_env_var(
+    name: &str,
+    value: &str,
+    comment: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<UpdateType, PathmanError> {
+    check_name(name)?;
+
+    // The value of the process is resolved before the variable is persisted,
+    // so that an invalid value doesn't leave the update half applied
+    let process_value = process::resolve_value(value, options)?;
+    let update_type = PlatformPathUpdater::set_var(name, value, comment, options)?;
+    process::set_process_var(options, name, Some(&process_value));
+
+    Ok(update_type)
+}
+
+/// Unsets the given environment variable persistently
+///
+/// [`UpdateType::Removed`] is returned if the variable was set, and
+/// [`UpdateType::NotInPath`] otherwise.
+///
+/// # OS-specific behavior
+///
+/// # macOS and Linux
+///
+/// On macOS and Linux, the lines of the shell configuration file setting the
+/// variable are removed, along with the preceding comment if it is the given
+/// comment, as written when the variable was set. Lines that were not written
+/// by this crate may be part of a compound command (e.g., an `if` block), in
+/// which case [`PathmanError::UnableToRemoveEntry`] is returned instead.
+///
+/// # Windows
+///
+/// On Windows, the variable is deleted from the user's environment in the
+/// registry.
+pub fn unset_env_var(
+    name: &str,
+    comment: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<UpdateType, PathmanError> {
+    check_name(name)?;
+    let update_type = PlatformPathUpdater::unset_var(name, comment, options)?;
+    process::set_process_var(options, name, None);
+
+    Ok(update_type)
+}
+
+/// Finds whether the given path is in the PATH environment variable, and
+/// where it is configured to be added to it
+///
+/// This function has no side effects, which makes it possible to check
+/// whether the PATH needs to be updated before prompting the user. Paths are
+/// compared according to the equivalence rules of the options.
+///
+/// # OS-specific behavior
+///
+/// # macOS and Linux
+///
+/// On macOS and Linux, the configuration files of all the supported shells
+/// are searched for lines adding the path to the PATH.
+///
+/// # Windows
+///
+/// On Windows, the user's and the system-wide PATH in the registry are
+/// searched for the path.
+pub fn find_in_path<P: AsRef<Path>>(
+    path: P,
+    options: &UpdateOptions,
+) -> Result<PathStatus, PathmanError> {
+    check_name(&options.variable)?;
+    let locations = PlatformPathUpdater::locate(&path, options)?;
+
+    let entry = match path.as_ref().to_str() {
+        Some(p) => p,
+        None => return Err(PathmanError::UnableToConvertPathToString),
+    };
+
+    let home = home_dir();
+    let normalized = options.equivalence.normalize(entry, home.as_deref());
+
+    let in_process = match var_os(&options.variable) {
+        Some(current_path) => split_paths(&current_path).any(|p| {
+            options
+                .equivalence
+                .normalize(&p.to_string_lossy(), home.as_deref())
+                == normalized
+        }),
+        None => false,
+    };
+
+    Ok(PathStatus {
+        in_process,
+        locations,
+    })
+}
+
+/// Returns the lines adding the given paths to the PATH environment variable
+/// at the given position, without modifying anything
+///
+/// This function is useful when the shell configuration file is managed
+/// declaratively (e.g., by home-manager), in which case updating it returns
+/// [`PathmanError::ManagedConfigFile`], so that the user can add the lines to
+/// their configuration instead.
+///
+/// # OS-specific behavior
+///
+/// # macOS and Linux
+///
+/// On macOS and Linux, the lines are written in the syntax of the current
+/// shell, preceded by the comment, as they would be added to its
+/// configuration file.
+///
+/// # Windows
+///
+/// On Windows, the PATH is stored in the registry, and
+/// [`PathmanError::UnsupportedTarget`] is returned.
+pub fn path_snippet<P: AsRef<Path>>(
+    paths: &[P],
+    position: &Position,
+    comment: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<String, PathmanError> {
+    check_name(&options.variable)?;
+    PlatformPathUpdater::snippet(paths, position, comment, options)
+}
+
+/// Rolls back the last change to the given file
+///
+/// The file is restored from its last backup, which is then deleted, so that
+/// restoring the file again rolls back the change before. A file that didn't
+/// exist before the change is deleted, as is a variable that wasn't set on
+/// Windows. The backups are looked up according to the
+/// [`UpdateOptions::backup`] settings, or to the default settings if they are
+/// not set, and [`PathmanError::NoBackup`] is returned when there is none.
+///
+/// # OS-specific behavior
+///
+/// # macOS and Linux
+///
+/// On macOS and Linux, the file is a configuration file that was updated
+/// (e.g., `/home/alice/.zshrc`). Symbolic links are followed, as when the
+/// file was updated.
+///
+/// # Windows
+///
+/// On Windows, the file is the name of the variable in the user's
+/// environment in the registry (e.g., `Path`), which is restored along with
+/// its registry type.
+pub fn restore_backup<P: AsRef<Path>>(
+    file: P,
+    options: &UpdateOptions,
+) -> Result<(), PathmanError> {
+    PlatformPathUpdater::restore(file, options)
+}
+
+/// Returns the commands activating the PATH environment variable updated
+/// with the given options in the current session
+///
+/// Updating the PATH only affects the shells started afterwards. The returned
+/// commands, one for each affected shell, apply the update to the current
+/// session instead of restarting the shell. No command is returned when the
+/// session cannot load the updated files, in which case it must be restarted.
+///
+/// # OS-specific behavior
+///
+/// # macOS and Linux
+///
+/// On macOS and Linux, the command loads the files updated for the target of
+/// the options in the shell of the options: it sources the configuration file
+/// of the shell (e.g., `source ~/.zshrc`) or the system-wide profile script,
+/// exports the assignments of `/etc/environment` or of the `environment.d`
+/// file in POSIX shells, or runs `path_helper` for `paths.d` files. Files
+/// outside the home directory of the session, such as those of the user who
+/// invoked `sudo`, are referred to by their absolute path. Nothing needs to
+/// be activated for staged roots and skeleton directories.
+///
+/// # Windows
+///
+/// On Windows, the command reloads the variable of the options from the
+/// registry in PowerShell, combining the system-wide and the user's values of
+/// the PATH.
+pub fn activation_commands(options: &UpdateOptions) -> Result<Vec<Activation>, PathmanError> {
+    check_name(&options.variable)?;
+    PlatformPathUpdater::activation(options)
+}
+
+/// Adds the given paths to the PATH of a child process at the given position
+///
+/// This function makes it possible to run a newly installed binary right
+/// away, for example for a post-install self-test, without restarting the
+/// shell. The PATH set on the command is updated if any, and the PATH of the
+/// current process is used otherwise. Unlike with
+/// [`UpdateOptions::update_process`], the environment of the current process
+/// is left untouched.
+pub fn add_to_command_path<P: AsRef<Path>>(
+    command: &mut Command,
+    paths: &[P],
+    position: &Position,
+    options: &UpdateOptions,
+) -> Result<(), PathmanError> {
+    check_name(&options.variable)?;
+    let paths: Vec<&Path> = paths.iter().map(AsRef::as_ref).collect();
+    let current = process::command_path(command, &options.variable);
+    let path = process::add_paths(&current, &paths, position, options)?;
+
+    command.env(&options.variable, path);
+
+    Ok(())
+}
+
+/// Returns an error if the given name is not a portable environment variable
+/// name
+///
+/// Names are written unquoted to the configuration files, where anything else
+/// could be interpreted as a command.
+fn check_name(name: &str) -> Result<(), PathmanError> {
+    let mut chars = name.chars();
+
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    match valid {
+        true => Ok(()),
+        false => Err(PathmanError::InvalidVariableName(name.to_string())),
+    }
 }
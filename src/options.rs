@@ -0,0 +1,489 @@
+use crate::backup::Backup;
+use crate::equivalence::Equivalence;
+use crate::error::PathmanError;
+#[cfg(unix)]
+use crate::error::PathmanError::{UnableToDetectShell, UnableToFindHomeDirectory};
+use crate::expand::Expansion;
+use crate::platform::{WriteHook, WrittenFile};
+use crate::target::Target;
+#[cfg(unix)]
+use crate::user::Account;
+use crate::user::User;
+#[cfg(unix)]
+use home::home_dir;
+#[cfg(unix)]
+use std::env::var_os;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Options for customizing how the PATH environment variable is updated
+///
+/// The default options produce the same result as [`prepend_to_path`] and
+/// [`append_to_path`].
+///
+/// [`prepend_to_path`]: crate::prepend_to_path
+/// [`append_to_path`]: crate::append_to_path
+#[derive(Debug, Clone)]
+pub struct UpdateOptions {
+    pub(crate) only_if_exists: bool,
+    pub(crate) home_relative: bool,
+    pub(crate) expansion: Expansion,
+    pub(crate) equivalence: Equivalence,
+    pub(crate) move_existing: bool,
+    pub(crate) update_process: bool,
+    pub(crate) variable: String,
+    pub(crate) literal: bool,
+    pub(crate) target: Target,
+    pub(crate) root: PathBuf,
+    #[cfg(windows)]
+    pub(crate) registry_key: String,
+    pub(crate) home: Option<PathBuf>,
+    pub(crate) shell: Option<String>,
+    pub(crate) user: User,
+    pub(crate) on_write: Option<WriteHook>,
+    pub(crate) managed_fallback: Option<Target>,
+    pub(crate) backup: Option<Backup>,
+}
+
+impl Default for UpdateOptions {
+    fn default() -> Self {
+        Self {
+            only_if_exists: false,
+            home_relative: true,
+            expansion: Expansion::default(),
+            equivalence: Equivalence::default(),
+            move_existing: false,
+            update_process: false,
+            variable: "PATH".to_string(),
+            literal: false,
+            target: Target::default(),
+            root: PathBuf::from("/"),
+            #[cfg(windows)]
+            registry_key: "Environment".to_string(),
+            home: None,
+            shell: None,
+            user: User::default(),
+            on_write: None,
+            managed_fallback: None,
+            backup: None,
+        }
+    }
+}
+
+impl UpdateOptions {
+    /// Creates the default set of options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only adds the entry when the directory exists at shell startup
+    ///
+    /// When enabled, the line written to the shell configuration file is
+    /// wrapped in an existence check (e.g. `[ -d "/x" ] && export ...` or
+    /// `test -d "/x"; and set -gx ...`), so that configuration files shared
+    /// across machines stay harmless where the directory does not exist.
+    ///
+    /// This option has no effect on Windows.
+    pub fn only_if_exists(mut self, only_if_exists: bool) -> Self {
+        self.only_if_exists = only_if_exists;
+        self
+    }
+
+    /// Writes paths located in the home directory relative to `$HOME`
+    ///
+    /// When enabled, which is the default, a path such as
+    /// `/home/alice/.biome/bin` is written as `$HOME/.biome/bin`, so that the
+    /// configuration file keeps working when the home directory moves or is
+    /// shared between machines. Both forms are recognized when checking
+    /// whether the path is already present.
+    ///
+    /// This option has no effect on Windows.
+    pub fn home_relative(mut self, home_relative: bool) -> Self {
+        self.home_relative = home_relative;
+        self
+    }
+
+    /// Sets how `~` and environment variables in the path are handled
+    ///
+    /// By default, `~`, `$VAR` and `${VAR}` (as well as `%VAR%` on Windows)
+    /// are expanded before updating the PATH, and an error is returned for
+    /// undefined variables. With [`Expansion::Deferred`], they are preserved
+    /// and written in the syntax of the target shell, so that they are
+    /// expanded whenever the PATH is read.
+    pub fn expansion(mut self, expansion: Expansion) -> Self {
+        self.expansion = expansion;
+        self
+    }
+
+    /// Sets the rules used to decide whether the path is already present
+    ///
+    /// See [`Equivalence`] for the available rules.
+    pub fn equivalence(mut self, equivalence: Equivalence) -> Self {
+        self.equivalence = equivalence;
+        self
+    }
+
+    /// Moves the path to the requested position if it is already present
+    /// elsewhere in the PATH
+    ///
+    /// By default, a path that is already present is left where it is,
+    /// regardless of its position, and [`UpdateType::AlreadyInPath`] is
+    /// returned. When enabled, an existing entry at the other end of the PATH
+    /// is removed and the path is added at the requested position, in which
+    /// case [`UpdateType::Moved`] is returned.
+    ///
+    /// On macOS and Linux, lines of the shell configuration file that add
+    /// other paths as well are left untouched.
+    ///
+    /// [`UpdateType::AlreadyInPath`]: crate::UpdateType::AlreadyInPath
+    /// [`UpdateType::Moved`]: crate::UpdateType::Moved
+    pub fn move_existing(mut self, move_existing: bool) -> Self {
+        self.move_existing = move_existing;
+        self
+    }
+
+    /// Sets the environment variable to update instead of the PATH
+    ///
+    /// Any list of directories separated by colons (or semicolons on Windows)
+    /// can be updated, such as `MANPATH`, `LD_LIBRARY_PATH`,
+    /// `PKG_CONFIG_PATH` or `XDG_DATA_DIRS`. Variables that may be unset are
+    /// updated without leaving an empty entry behind, except for the
+    /// `MANPATH`, where an empty entry stands for the default search path and
+    /// is therefore kept. `XDG_DATA_DIRS` and `XDG_CONFIG_DIRS` start from
+    /// their default directories when they are unset.
+    ///
+    /// Names that are not valid environment variable names are rejected with
+    /// [`PathmanError::InvalidVariableName`] when the options are used.
+    ///
+    /// [`PathmanError::InvalidVariableName`]: crate::PathmanError::InvalidVariableName
+    pub fn variable(mut self, variable: &str) -> Self {
+        self.variable = variable.to_string();
+        self
+    }
+
+    /// Sets whether the values set by [`set_env_var`] are written literally
+    ///
+    /// By default, values are handled like paths: `~` and environment
+    /// variables are expanded, values in the home directory are written
+    /// relative to it, and values are compared according to the equivalence
+    /// rules of the options. Literal values are written as is, without being
+    /// expanded by the shell or by Windows, and are only considered already
+    /// set when they are identical.
+    ///
+    /// [`set_env_var`]: crate::set_env_var
+    pub fn literal(mut self, literal: bool) -> Self {
+        self.literal = literal;
+        self
+    }
+
+    /// Sets the files updated to persist the change
+    ///
+    /// By default, the configuration file of the current shell is updated.
+    /// See [`Target`] for the other targets, which are only supported on
+    /// macOS and Linux.
+    pub fn target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Sets the root directory under which all the updated files are located
+    ///
+    /// By default, files are written relative to `/`. A different root, such
+    /// as the `DESTDIR` of a package or the staging directory of an OS image,
+    /// makes it possible to update the files of another system, or to
+    /// exercise system-wide updates without root privileges. The shell
+    /// configuration files of the user are located by joining their path
+    /// (e.g., `/home/builder/.zshrc`) onto the root, while the paths written to
+    /// the files remain relative to the target system.
+    ///
+    /// With a root other than `/`, the `HOME` and `SHELL` environment
+    /// variables of the current process are never read, so the home directory
+    /// and the shell must be set with [`UpdateOptions::home`] and
+    /// [`UpdateOptions::shell`] when they are needed. System-wide targets
+    /// only need the home directory to expand `~` and `$HOME` in paths.
+    ///
+    /// This option has no effect on Windows.
+    pub fn root<P: AsRef<Path>>(mut self, root: P) -> Self {
+        self.root = root.as_ref().to_path_buf();
+        self
+    }
+
+    /// Sets the registry key, under `HKEY_CURRENT_USER`, whose variables are
+    /// updated instead of those of the `Environment` key
+    ///
+    /// This is only meant for the tests, which update the variables of a
+    /// scratch key rather than the environment of the user.
+    #[cfg(all(windows, feature = "__registry-key"))]
+    #[doc(hidden)]
+    pub fn registry_key(mut self, registry_key: &str) -> Self {
+        self.registry_key = registry_key.to_string();
+        self
+    }
+
+    /// Sets the home directory of the user whose files are updated, instead
+    /// of the home directory of the current user
+    ///
+    /// The home directory is given as seen from the target system, so it is
+    /// joined onto the root directory of the options to locate the files.
+    ///
+    /// This option has no effect on Windows.
+    pub fn home<P: AsRef<Path>>(mut self, home: P) -> Self {
+        self.home = Some(home.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the shell whose configuration file is updated (e.g., `zsh` or
+    /// `/bin/bash`), instead of the shell of the `SHELL` environment variable
+    ///
+    /// This option has no effect on Windows.
+    pub fn shell(mut self, shell: &str) -> Self {
+        self.shell = Some(shell.to_string());
+        self
+    }
+
+    /// Sets the user whose shell configuration files are updated, instead of
+    /// the current user
+    ///
+    /// The home directory and the login shell of the user are read from the
+    /// user database of the system, or from the `/etc/passwd` file of the
+    /// root directory when it isn't `/`, unless they are set with
+    /// [`UpdateOptions::home`] and [`UpdateOptions::shell`]. The files created
+    /// for the user, along with their parent directories, are owned by the
+    /// user, while existing files keep their ownership. See [`User`] for the
+    /// available users.
+    ///
+    /// This option has no effect on Windows.
+    pub fn user(mut self, user: User) -> Self {
+        self.user = user;
+        self
+    }
+
+    /// Sets a function called with each file written, along with the
+    /// strategy used to write it
+    ///
+    /// Files are written to a temporary file in the same directory, which
+    /// then replaces them, carrying over their permissions, ownership and
+    /// extended attributes, so that a crash or a full disk never leaves them
+    /// truncated. When they can't be replaced, for example because they are
+    /// bind-mounted, they are overwritten in place instead, and the function
+    /// is called with [`WriteStrategy::InPlace`].
+    ///
+    /// Symbolic links, such as dotfiles managed by GNU Stow or chezmoi, are
+    /// followed so that their target is edited, which the function receives
+    /// as the [`WrittenFile::target`] of the link.
+    ///
+    /// The function may capture state, such as a list collecting the files
+    /// written.
+    ///
+    /// This option has no effect on Windows.
+    ///
+    /// [`WriteStrategy::InPlace`]: crate::WriteStrategy::InPlace
+    pub fn on_write<F>(mut self, on_write: F) -> Self
+    where
+        F: Fn(&WrittenFile) + Send + Sync + 'static,
+    {
+        self.on_write = Some(WriteHook(Arc::new(on_write)));
+        self
+    }
+
+    /// Sets the target updated instead of the shell configuration file when
+    /// the latter is managed declaratively
+    ///
+    /// With home-manager, the shell configuration files are symbolic links
+    /// into the read-only Nix store, which must be changed through the Nix
+    /// configuration instead. By default, updating them returns
+    /// [`PathmanError::ManagedConfigFile`], and [`path_snippet`] returns the
+    /// lines to add to the configuration. With a fallback target, such as a
+    /// systemd `environment.d` file, the update is written there instead.
+    ///
+    /// This option has no effect on Windows.
+    ///
+    /// [`PathmanError::ManagedConfigFile`]: crate::PathmanError::ManagedConfigFile
+    /// [`path_snippet`]: crate::path_snippet
+    pub fn managed_fallback(mut self, target: Target) -> Self {
+        self.managed_fallback = Some(target);
+        self
+    }
+
+    /// Backs up the files before they are modified
+    ///
+    /// Each file is copied before every change, along with its permissions,
+    /// and [`restore_backup`] rolls back the last change to a file. On
+    /// Windows, the value of the variable in the registry is backed up
+    /// instead. See [`Backup`] for where the backups are stored and how many
+    /// are kept.
+    ///
+    /// [`restore_backup`]: crate::restore_backup
+    pub fn backup(mut self, backup: Backup) -> Self {
+        self.backup = Some(backup);
+        self
+    }
+
+    /// Returns the backup settings of the options, with the state directory
+    /// of the user as the default directory
+    ///
+    /// `XDG_STATE_HOME` is only used for the current user on the current
+    /// system, as it belongs to the current process.
+    #[cfg(unix)]
+    pub(crate) fn backup_settings(&self) -> Result<Option<Backup>, PathmanError> {
+        let Some(backup) = &self.backup else {
+            return Ok(None);
+        };
+        if backup.next_to_file {
+            // Every file of these directories is read, or copied to the home
+            // directory of new users
+            let outside_directory = matches!(
+                self.target,
+                Target::PathsD { .. }
+                    | Target::Skeleton { .. }
+                    | Target::ProfileD { .. }
+                    | Target::EnvironmentD { .. }
+            );
+
+            let backup = backup.clone().outside_directory(outside_directory);
+
+            // The whole skeleton directory is copied, including the
+            // subdirectories holding the configuration files (e.g.,
+            // `.config/fish`), so its backups are stored next to it
+            return Ok(Some(match &self.target {
+                Target::Skeleton { dir } => {
+                    backup.top_directory(self.root.join(dir.strip_prefix("/").unwrap_or(dir)))
+                }
+                _ => backup,
+            }));
+        }
+        if backup.dir.is_some() {
+            return Ok(Some(backup.clone()));
+        }
+
+        let state_home = var_os("XDG_STATE_HOME")
+            .filter(|_| !self.is_staged() && self.user == User::Current)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute());
+        let state_home = match state_home {
+            Some(state_home) => state_home,
+            None => {
+                let home = self.home_dir()?;
+                let home = home.strip_prefix("/").unwrap_or(&home);
+
+                self.root.join(home).join(".local/state")
+            }
+        };
+
+        Ok(Some(backup.clone().dir(state_home.join("pathman/backups"))))
+    }
+
+    /// Returns the backup settings of the options, with the local
+    /// application data directory of the user as the default directory
+    #[cfg(windows)]
+    pub(crate) fn backup_settings(&self) -> Result<Option<Backup>, PathmanError> {
+        let Some(backup) = &self.backup else {
+            return Ok(None);
+        };
+        if backup.dir.is_some() {
+            return Ok(Some(backup.clone()));
+        }
+
+        let local_app_data = match std::env::var_os("LOCALAPPDATA") {
+            Some(local_app_data) => PathBuf::from(local_app_data),
+            None => match home::home_dir() {
+                Some(home) => home.join(r"AppData\Local"),
+                None => return Err(PathmanError::UnableToFindHomeDirectory),
+            },
+        };
+
+        Ok(Some(
+            backup.clone().dir(local_app_data.join(r"pathman\backups")),
+        ))
+    }
+
+    /// Returns whether the files are updated under a root other than `/`
+    #[cfg(unix)]
+    pub(crate) fn is_staged(&self) -> bool {
+        self.root != Path::new("/")
+    }
+
+    /// Returns the home directory of the options, or the home directory of
+    /// the user of the options, or the home directory of the current user
+    /// unless the files are staged
+    #[cfg(unix)]
+    pub(crate) fn home_dir(&self) -> Result<PathBuf, PathmanError> {
+        if self.home.is_none()
+            && let Some(account) = self.account()?
+        {
+            return Ok(account.home);
+        }
+
+        match (&self.home, self.is_staged()) {
+            (Some(home), _) => Ok(home.clone()),
+            (None, false) => home_dir().ok_or(UnableToFindHomeDirectory),
+            (None, true) => Err(UnableToFindHomeDirectory),
+        }
+    }
+
+    /// Returns the home directory of the options like [`Self::home_dir`], or
+    /// `None` when the files are staged without a home directory
+    ///
+    /// System-wide files only need the home directory to expand `~` and
+    /// `$HOME` in the paths written to them.
+    #[cfg(unix)]
+    pub(crate) fn find_home_dir(&self) -> Result<Option<PathBuf>, PathmanError> {
+        match self.home_dir() {
+            Ok(home) => Ok(Some(home)),
+            Err(UnableToFindHomeDirectory) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the shell of the options, or the login shell of the user of
+    /// the options, or the shell of the `SHELL` environment variable unless
+    /// the files are staged
+    #[cfg(unix)]
+    pub(crate) fn shell_name(&self) -> Result<String, PathmanError> {
+        if self.shell.is_none()
+            && let Some(account) = self.account()?
+        {
+            return Ok(account.shell);
+        }
+
+        match (&self.shell, self.is_staged()) {
+            (Some(shell), _) => Ok(shell.clone()),
+            (None, false) => match var_os("SHELL") {
+                Some(shell) => Ok(shell.to_string_lossy().to_string()),
+                None => Err(UnableToDetectShell),
+            },
+            (None, true) => Err(UnableToDetectShell),
+        }
+    }
+
+    /// Returns the account of the user of the options, or `None` for the
+    /// current user
+    #[cfg(unix)]
+    pub(crate) fn account(&self) -> Result<Option<Account>, PathmanError> {
+        Account::find(&self.user, &self.root)
+    }
+
+    /// Updates the PATH of the current process as well
+    ///
+    /// When enabled, the path is also added to the PATH environment variable
+    /// of the current process, at the requested position and unless it is
+    /// already present, so that newly installed binaries can be run right
+    /// away. Child processes spawned afterwards inherit the updated PATH.
+    ///
+    /// To update the PATH of a single child process instead, use
+    /// [`add_to_command_path`], which doesn't require `unsafe`.
+    ///
+    /// # Safety
+    ///
+    /// Updating the environment of the current process is only safe when no
+    /// other thread reads or writes the environment at the same time, as
+    /// documented in [`std::env::set_var`]. The caller must ensure this
+    /// holds whenever the PATH is updated with these options, for example by
+    /// updating it before spawning any threads.
+    ///
+    /// [`add_to_command_path`]: crate::add_to_command_path
+    pub unsafe fn update_process(mut self, update_process: bool) -> Self {
+        self.update_process = update_process;
+        self
+    }
+}
@@ -0,0 +1,109 @@
+use crate::error::PathmanError;
+use crate::error::PathmanError::UnableToReadShellConfigFile;
+use std::path::Path;
+
+/// A simulation of `path_helper`, which builds the PATH of login shells on
+/// macOS
+///
+/// `path_helper` puts the entries of `/etc/paths` first, followed by the
+/// entries of the files in `/etc/paths.d` in lexical order, and only then the
+/// other entries of the current PATH. When it runs after a directory was
+/// prepended to the PATH (e.g., in `~/.zshenv`), that directory is therefore
+/// moved after the system directories.
+///
+/// ```
+/// use pathman::PathHelper;
+///
+/// let helper = PathHelper::parse("/usr/bin\n/bin\n", &["/opt/tool/bin\n"]);
+///
+/// assert_eq!(
+///     "/usr/bin:/bin:/opt/tool/bin:/opt/other/bin",
+///     helper.apply("/opt/other/bin:/usr/bin")
+/// );
+/// assert!(helper.reorders("/opt/other/bin", "/usr/bin:/bin"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathHelper {
+    entries: Vec<String>,
+}
+
+impl PathHelper {
+    /// Creates the simulation from the content of `/etc/paths` and of the
+    /// files in `/etc/paths.d`, in lexical order of their names
+    pub fn parse(paths: &str, paths_d: &[&str]) -> Self {
+        let mut entries: Vec<String> = Vec::new();
+
+        for line in std::iter::once(paths)
+            .chain(paths_d.iter().copied())
+            .flat_map(str::lines)
+        {
+            let line = line.trim();
+            if !line.is_empty() && !entries.iter().any(|e| e == line) {
+                entries.push(line.to_string());
+            }
+        }
+
+        Self { entries }
+    }
+
+    /// Reads `/etc/paths` and the files in `/etc/paths.d` under the given
+    /// root directory
+    ///
+    /// Missing files and directories are considered empty.
+    pub fn read<P: AsRef<Path>>(root: P) -> Result<Self, PathmanError> {
+        let root = root.as_ref();
+        let read = |path: &Path| match std::fs::read_to_string(path) {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(_) => Err(UnableToReadShellConfigFile(
+                path.to_string_lossy().to_string(),
+            )),
+        };
+
+        let paths = read(&root.join("etc/paths"))?;
+
+        let mut files: Vec<_> = match std::fs::read_dir(root.join("etc/paths.d")) {
+            Ok(entries) => entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        files.sort();
+
+        let paths_d = files
+            .iter()
+            .map(|file| read(file))
+            .collect::<Result<Vec<_>, _>>()?;
+        let paths_d: Vec<&str> = paths_d.iter().map(String::as_str).collect();
+
+        Ok(Self::parse(&paths, &paths_d))
+    }
+
+    /// Returns the entries added by `path_helper`, in order
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Returns the PATH produced by `path_helper` from the given PATH
+    pub fn apply(&self, current: &str) -> String {
+        let mut entries = self.entries.clone();
+
+        for entry in current.split(':').filter(|e| !e.is_empty()) {
+            if !entries.iter().any(|e| e == entry) {
+                entries.push(entry.to_string());
+            }
+        }
+
+        entries.join(":")
+    }
+
+    /// Returns whether prepending the given directory to the given PATH
+    /// before `path_helper` runs would not leave it at the front of the PATH
+    pub fn reorders(&self, path: &str, current: &str) -> bool {
+        let prepended = format!("{path}:{current}");
+
+        self.apply(&prepended).split(':').next() != Some(path)
+    }
+}
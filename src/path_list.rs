@@ -0,0 +1,279 @@
+use crate::equivalence::Equivalence;
+use crate::platform::{Position, UpdateType};
+use home::home_dir;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// A list of directories, as stored in the PATH environment variable
+///
+/// The list is parsed from and serialized to a string using a separator,
+/// preserving the entries as they are written, including empty entries, which
+/// refer to the working directory. Entries are compared according to the
+/// equivalence rules of the list, so that `/usr/local/bin` and
+/// `/usr/local/bin/` are considered the same entry.
+///
+/// ```
+/// use pathman::{PathList, Position};
+///
+/// let mut list = PathList::parse("/usr/bin:/bin", ':');
+/// list.insert("/opt/tool/bin", &Position::Front);
+///
+/// assert!(list.contains("/opt/tool/bin/"));
+/// assert_eq!("/opt/tool/bin:/usr/bin:/bin", list.to_string());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathList {
+    entries: Vec<String>,
+    separator: char,
+    equivalence: Equivalence,
+    home: Option<PathBuf>,
+}
+
+impl PathList {
+    /// The separator of the PATH environment variable on the current platform
+    pub const SEPARATOR: char = if cfg!(windows) { ';' } else { ':' };
+
+    /// Creates an empty list using the given separator
+    pub fn new(separator: char) -> Self {
+        Self {
+            entries: Vec::new(),
+            separator,
+            equivalence: Equivalence::default(),
+            home: home_dir(),
+        }
+    }
+
+    /// Parses the given value using the given separator
+    ///
+    /// An empty value is parsed as an empty list, while empty entries between
+    /// separators are preserved.
+    pub fn parse(value: &str, separator: char) -> Self {
+        let mut list = Self::new(separator);
+
+        if !value.is_empty() {
+            list.entries = value.split(separator).map(String::from).collect();
+        }
+
+        list
+    }
+
+    /// Sets the rules used to decide whether two entries are the same
+    pub fn equivalence(mut self, equivalence: Equivalence) -> Self {
+        self.equivalence = equivalence;
+        self
+    }
+
+    /// Sets the home directory used to expand `~` when comparing entries.
+    pub(crate) fn home(mut self, home: Option<&Path>) -> Self {
+        self.home = home.map(Path::to_path_buf);
+        self
+    }
+
+    /// Returns the entries of the list
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Returns the number of entries in the list
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the list has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn normalize(&self, entry: &str) -> String {
+        self.equivalence.normalize(entry, self.home.as_deref())
+    }
+
+    /// Returns whether the list contains an entry equivalent to the given one
+    pub fn contains(&self, entry: &str) -> bool {
+        self.position(entry).is_some()
+    }
+
+    /// Returns the index of the first entry equivalent to the given one
+    pub fn position(&self, entry: &str) -> Option<usize> {
+        let entry = self.normalize(entry);
+
+        self.entries.iter().position(|e| self.normalize(e) == entry)
+    }
+
+    /// Returns whether an entry equivalent to the given one is at the given
+    /// position
+    ///
+    /// When the anchor of the position is in the list, the entry must be
+    /// directly next to it. Otherwise, it must be at the fallback end of the
+    /// list.
+    pub fn is_at(&self, entry: &str, position: &Position) -> bool {
+        let entry = self.normalize(entry);
+        let is_entry = |e: &String| self.normalize(e) == entry;
+
+        match (position, self.anchor_index(position)) {
+            (Position::Before { .. }, Some(index)) => {
+                index > 0 && is_entry(&self.entries[index - 1])
+            }
+            (Position::After { .. }, Some(index)) => {
+                self.entries.get(index + 1).is_some_and(is_entry)
+            }
+            (position, _) => match position.fallback() {
+                Position::Front => self.entries.first().is_some_and(is_entry),
+                _ => self.entries.last().is_some_and(is_entry),
+            },
+        }
+    }
+
+    /// Returns the index of the anchor of the given position, if any
+    fn anchor_index(&self, position: &Position) -> Option<usize> {
+        match position {
+            Position::Before { anchor, .. } | Position::After { anchor, .. } => {
+                self.position(&anchor.to_string_lossy())
+            }
+            _ => None,
+        }
+    }
+
+    /// Inserts the given entry at the given position
+    ///
+    /// The entry is inserted even if an equivalent entry is already present.
+    pub fn insert(&mut self, entry: &str, position: &Position) {
+        self.insert_all(&[entry], position);
+    }
+
+    /// Inserts the given entries at the given position, keeping their order
+    ///
+    /// The entries are inserted even if equivalent entries are already
+    /// present.
+    pub fn insert_all(&mut self, entries: &[&str], position: &Position) {
+        let index = match (position, self.anchor_index(position)) {
+            (Position::Before { .. }, Some(index)) => index,
+            (Position::After { .. }, Some(index)) => index + 1,
+            (position, _) => match position.fallback() {
+                Position::Front => 0,
+                _ => self.entries.len(),
+            },
+        };
+
+        self.entries
+            .splice(index..index, entries.iter().map(|e| e.to_string()));
+    }
+
+    /// Removes all the entries equivalent to the given one, returning whether
+    /// any entry was removed
+    pub fn remove(&mut self, entry: &str) -> bool {
+        let entry = self.normalize(entry);
+        let len = self.entries.len();
+
+        let entries = std::mem::take(&mut self.entries);
+        self.entries = entries
+            .into_iter()
+            .filter(|e| self.normalize(e) != entry)
+            .collect();
+
+        self.entries.len() != len
+    }
+
+    /// Moves the entries equivalent to the given one to the given position,
+    /// returning whether any entry was moved
+    ///
+    /// The entry is written as given, replacing the existing ones.
+    pub fn move_to(&mut self, entry: &str, position: &Position) -> bool {
+        if !self.remove(entry) {
+            return false;
+        }
+
+        self.insert(entry, position);
+        true
+    }
+
+    /// Replaces the first entry equivalent to the old one with the new one,
+    /// and removes the other entries equivalent to the old one
+    ///
+    /// If the new entry is already present, it is left where it is. Returns
+    /// whether the old entry was found.
+    pub fn replace(&mut self, old: &str, new: &str) -> bool {
+        let Some(index) = self.position(old) else {
+            return false;
+        };
+
+        // Keep the position of the new entry if it is already present
+        if !self.contains(new) {
+            self.entries[index] = new.to_string();
+        }
+        if self.normalize(old) != self.normalize(new) {
+            self.remove(old);
+        }
+
+        true
+    }
+
+    /// Adds the given entries at the given position, unless equivalent
+    /// entries are already present, returning the update type of each entry.
+    ///
+    /// Entries that are already present are moved to the position if
+    /// `move_existing` is set.
+    pub(crate) fn update(
+        &mut self,
+        entries: &[&str],
+        position: &Position,
+        move_existing: bool,
+    ) -> Vec<UpdateType> {
+        let mut update_types = Vec::new();
+        let mut added = PathList::new(self.separator)
+            .equivalence(self.equivalence)
+            .home(self.home.as_deref());
+
+        for entry in entries {
+            // The same entry may be given more than once
+            if added.contains(entry) {
+                update_types.push(UpdateType::AlreadyInPath);
+                continue;
+            }
+
+            if !self.contains(entry) {
+                update_types.push(UpdateType::Success);
+            } else if !move_existing || self.is_at(entry, position) {
+                update_types.push(UpdateType::AlreadyInPath);
+                continue;
+            } else {
+                update_types.push(UpdateType::Moved);
+            }
+
+            added.entries.push(entry.to_string());
+        }
+
+        // Moved entries are removed before inserting, so that the insertion
+        // index accounts for them
+        for entry in &added.entries {
+            self.remove(entry);
+        }
+
+        let added: Vec<&str> = added.entries.iter().map(String::as_str).collect();
+        self.insert_all(&added, position);
+
+        update_types
+    }
+
+    /// Removes the entries equivalent to an earlier entry
+    pub fn dedupe(&mut self) {
+        let mut seen = Vec::new();
+
+        let entries = std::mem::take(&mut self.entries);
+        for entry in entries {
+            let normalized = self.normalize(&entry);
+            if !seen.contains(&normalized) {
+                seen.push(normalized);
+                self.entries.push(entry);
+            }
+        }
+    }
+}
+
+impl Display for PathList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let separator = self.separator.to_string();
+
+        write!(f, "{}", self.entries.join(&separator))
+    }
+}
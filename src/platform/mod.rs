@@ -1,5 +1,10 @@
+use crate::activation::Activation;
 use crate::error::PathmanError;
-use std::path::Path;
+use crate::options::UpdateOptions;
+use crate::status::Location;
+use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[cfg(unix)]
 mod unix;
@@ -15,17 +20,223 @@ pub use windows::WindowsPathUpdater as PlatformPathUpdater;
 
 pub trait PathUpdater {
     /// Prepends the given path to the PATH environment variable.
-    fn prepend<P: AsRef<Path>>(path: P, comment: Option<&str>) -> Result<UpdateType, PathmanError>;
+    fn prepend<P: AsRef<Path>>(
+        path: P,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError>;
 
     /// Appends the given path to the PATH environment variable.
-    fn append<P: AsRef<Path>>(path: P, comment: Option<&str>) -> Result<UpdateType, PathmanError>;
+    fn append<P: AsRef<Path>>(
+        path: P,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError>;
+
+    /// Adds the given path to the PATH environment variable at the given position.
+    fn insert<P: AsRef<Path>>(
+        path: P,
+        position: &Position,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError>;
+
+    /// Adds the given paths to the PATH environment variable at the given
+    /// position, in a single update.
+    ///
+    /// The update type of each path is returned in the order of the input.
+    fn insert_all<P: AsRef<Path>>(
+        paths: &[P],
+        position: &Position,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<Vec<UpdateType>, PathmanError>;
+
+    /// Finds the places where the given path is configured to be added to
+    /// the PATH environment variable, without modifying them.
+    fn locate<P: AsRef<Path>>(
+        path: P,
+        options: &UpdateOptions,
+    ) -> Result<Vec<Location>, PathmanError>;
+
+    /// Returns the lines that add the given paths to the PATH environment
+    /// variable at the given position, for the user to add to their
+    /// configuration.
+    fn snippet<P: AsRef<Path>>(
+        paths: &[P],
+        position: &Position,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<String, PathmanError>;
+
+    /// Rolls back the last change to the given file from its backups.
+    fn restore<P: AsRef<Path>>(file: P, options: &UpdateOptions) -> Result<(), PathmanError>;
+
+    /// Returns the commands activating the PATH environment variable updated
+    /// with the given options in the current session, for each affected
+    /// shell.
+    fn activation(options: &UpdateOptions) -> Result<Vec<Activation>, PathmanError>;
+
+    /// Removes the given path from the PATH environment variable, along with
+    /// the given comment.
+    fn remove<P: AsRef<Path>>(
+        path: P,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError>;
+
+    /// Sets the given environment variable to the given value.
+    fn set_var(
+        name: &str,
+        value: &str,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError>;
+
+    /// Unsets the given environment variable, along with the given comment.
+    fn unset_var(
+        name: &str,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError>;
+
+    /// Replaces the old path with the new path in the PATH environment variable.
+    fn replace<O: AsRef<Path>, N: AsRef<Path>>(
+        old: O,
+        new: N,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError>;
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// The outcome of an update
+///
+/// New outcomes may be added as new operations are supported, so matches on
+/// this type need a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum UpdateType {
     /// Indicates that the path was successfully added to the PATH environment variable.
     Success,
 
     /// Indicates that the path was already present in the PATH environment variable.
     AlreadyInPath,
+
+    /// Indicates that the path was already present in the PATH environment variable,
+    /// and was moved to the requested position.
+    Moved,
+
+    /// Indicates that the old path was replaced with the new path in the PATH
+    /// environment variable.
+    Replaced,
+
+    /// Indicates that the path was removed from the PATH environment variable.
+    Removed,
+
+    /// Indicates that the path to remove was not in the PATH environment variable.
+    NotInPath,
+}
+
+/// The position at which an entry is added to the PATH environment variable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Position {
+    /// The beginning of the PATH, where entries are searched first.
+    Front,
+
+    /// The end of the PATH, where entries are searched last.
+    Back,
+
+    /// Directly before the anchor entry, or at the fallback position if the
+    /// anchor is not in the PATH.
+    Before { anchor: PathBuf, fallback: Fallback },
+
+    /// Directly after the anchor entry, or at the fallback position if the
+    /// anchor is not in the PATH.
+    After { anchor: PathBuf, fallback: Fallback },
+}
+
+/// The end of the PATH environment variable at which an entry is added when
+/// its anchor is not in the PATH
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fallback {
+    /// The beginning of the PATH.
+    Front,
+
+    /// The end of the PATH.
+    Back,
+}
+
+/// How a file was written when persisting an update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteStrategy {
+    /// The content was written to a temporary file in the same directory,
+    /// which then replaced the file, so that the file is never left partially
+    /// written.
+    Atomic,
+
+    /// The file was overwritten in place, because it couldn't be replaced
+    /// (e.g., a bind-mounted file, or a directory that isn't writable).
+    InPlace,
+}
+
+/// A file written when persisting an update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrittenFile {
+    /// The path of the updated file (e.g., `~/.zshrc`)
+    pub path: PathBuf,
+
+    /// The file actually written, which is the target of the path when it is
+    /// a symbolic link (e.g., `~/dotfiles/zsh/.zshrc`), or the path itself
+    pub target: PathBuf,
+
+    /// How the file was written
+    pub strategy: WriteStrategy,
+}
+
+/// A function called with each file written
+type WriteFn = dyn Fn(&WrittenFile) + Send + Sync;
+
+/// The function called whenever a file is written, shared by the copies of
+/// the options
+#[derive(Clone)]
+pub(crate) struct WriteHook(pub Arc<WriteFn>);
+
+impl Debug for WriteHook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WriteHook({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
+impl Position {
+    /// Creates a position directly before the given anchor entry
+    pub fn before<P: AsRef<Path>>(anchor: P, fallback: Fallback) -> Self {
+        Position::Before {
+            anchor: anchor.as_ref().to_path_buf(),
+            fallback,
+        }
+    }
+
+    /// Creates a position directly after the given anchor entry
+    pub fn after<P: AsRef<Path>>(anchor: P, fallback: Fallback) -> Self {
+        Position::After {
+            anchor: anchor.as_ref().to_path_buf(),
+            fallback,
+        }
+    }
+
+    /// Returns the end of the PATH at which the entry is added when the
+    /// anchor, if any, is not in the PATH.
+    pub(crate) fn fallback(&self) -> Position {
+        match self {
+            Position::Front
+            | Position::Before {
+                fallback: Fallback::Front,
+                ..
+            }
+            | Position::After {
+                fallback: Fallback::Front,
+                ..
+            } => Position::Front,
+            _ => Position::Back,
+        }
+    }
 }
@@ -0,0 +1,508 @@
+use crate::backup::Backup;
+use crate::error::PathmanError;
+use crate::error::PathmanError::{
+    InsufficientPermissions, NoBackup, UnableToReadShellConfigFile, UnableToWriteShellConfigFile,
+};
+use crate::options::UpdateOptions;
+use crate::platform::unix::shell::CurrentShell;
+use crate::platform::{Position, WriteHook, WriteStrategy, WrittenFile};
+use std::fs::{File, OpenOptions, Permissions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// A shell configuration file, edited line by line
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub lines: Vec<String>,
+
+    /// Whether the file is shared by all the users of the system
+    pub system: bool,
+
+    /// The UID and GID given to the file and its parent directories when
+    /// they are created, or `None` to keep the ones of the current process
+    pub owner: Option<(u32, u32)>,
+
+    /// The function called with the strategy used to write the file
+    pub on_write: Option<WriteHook>,
+
+    /// The settings for backing up the file before it is modified, if any
+    pub backup: Option<Backup>,
+}
+
+/// A line of a configuration file adding an entry to the PATH
+pub struct Match {
+    /// The index of the line in the configuration file
+    pub line: usize,
+
+    /// The entry, as written in the configuration file
+    pub value: String,
+
+    /// Whether the entry is added to the front or the back of the PATH
+    pub position: Position,
+
+    /// Whether the line adds other entries to the PATH as well
+    pub shared: bool,
+}
+
+/// A line of a configuration file assigning a value to a variable
+pub struct Assignment {
+    /// The index of the line in the configuration file
+    pub line: usize,
+
+    /// The value, as written in the configuration file
+    pub value: String,
+}
+
+impl ConfigFile {
+    /// Reads the configuration file at the given path
+    pub fn read(path: PathBuf) -> Result<Self, PathmanError> {
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => {
+                return Err(UnableToReadShellConfigFile(
+                    path.to_string_lossy().to_string(),
+                ));
+            }
+        };
+
+        // Splitting on newlines only, rather than using `lines`, allows
+        // writing the content back exactly as it was read
+        let lines = content.split('\n').map(String::from).collect();
+
+        Ok(Self {
+            path,
+            lines,
+            system: false,
+            owner: None,
+            on_write: None,
+            backup: None,
+        })
+    }
+
+    /// Marks the file as shared by all the users of the system
+    pub fn system(mut self, system: bool) -> Self {
+        self.system = system;
+        self
+    }
+
+    /// Sets the owner of the file and its parent directories, when they are
+    /// created
+    pub fn owner(mut self, owner: Option<(u32, u32)>) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    /// Sets the function called with the strategy used to write the file
+    pub fn on_write(mut self, on_write: Option<WriteHook>) -> Self {
+        self.on_write = on_write;
+        self
+    }
+
+    /// Sets the settings for backing up the file before it is modified
+    pub fn backup(mut self, backup: Option<Backup>) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Reads the configuration file at the given path, or starts an empty
+    /// one if it does not exist yet
+    ///
+    /// The file and its parent directories are only created when it is
+    /// written.
+    pub fn open(path: PathBuf) -> Result<Self, PathmanError> {
+        match path.exists() {
+            true => Self::read(path),
+            false => Ok(Self {
+                path,
+                lines: Vec::new(),
+                system: false,
+                owner: None,
+                on_write: None,
+                backup: None,
+            }),
+        }
+    }
+
+    /// Finds the lines adding an entry equivalent to the given one to the
+    /// variable of the options
+    pub fn find(&self, shell: &CurrentShell, entry: &str, options: &UpdateOptions) -> Vec<Match> {
+        let equivalence = &options.equivalence;
+        let entry = equivalence.normalize(entry, shell.home.as_deref());
+
+        let mut matches = Vec::new();
+
+        for (index, line) in self.lines.iter().enumerate() {
+            let entries = shell.parse_entries(line, &options.variable);
+            let shared = entries.len() > 1;
+
+            for existing in entries {
+                if equivalence.normalize(&existing.value, shell.home.as_deref()) == entry {
+                    matches.push(Match {
+                        line: index,
+                        value: existing.value,
+                        position: existing.position,
+                        shared,
+                    });
+                }
+            }
+        }
+
+        matches
+    }
+
+    /// Finds the lines assigning a value to the given variable
+    pub fn find_assignments(&self, shell: &CurrentShell, name: &str) -> Vec<Assignment> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                shell
+                    .parse_assignment(line, name)
+                    .map(|value| Assignment { line: index, value })
+            })
+            .collect()
+    }
+
+    /// Appends the given line to the file, preceded by the given comment
+    pub fn push(&mut self, comment: Option<&str>, line: &str) {
+        if let Some(comment) = comment {
+            self.lines.push(format!("# {comment}"));
+        }
+
+        self.lines.push(line.to_string());
+    }
+
+    /// Removes the lines at the given indices from the file
+    ///
+    /// A comment directly preceding a removed line is removed as well if it
+    /// is the given comment, as written by [`ConfigFile::push`], while other
+    /// comments are left to the user.
+    pub fn remove(&mut self, indices: &[usize], comment: Option<&str>) {
+        let comment = comment.map(|comment| format!("# {comment}"));
+
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+
+        for index in indices.into_iter().rev() {
+            self.lines.remove(index);
+
+            let mut start = index;
+            if start > 0 && comment.as_ref() == Some(&self.lines[start - 1]) {
+                start -= 1;
+                self.lines.remove(start);
+            }
+
+            // Avoid leaving consecutive blank lines behind
+            let is_blank = |line: Option<&String>| line.is_some_and(|l| l.trim().is_empty());
+            if start > 0 && is_blank(self.lines.get(start - 1)) && is_blank(self.lines.get(start)) {
+                self.lines.remove(start);
+            }
+        }
+    }
+
+    /// Writes the content back to the configuration file, or to the target
+    /// of the symbolic link at its path
+    ///
+    /// Existing files keep their ownership, while the file and the parent
+    /// directories created are given to the owner of the file, if any.
+    pub fn write(&self) -> Result<(), PathmanError> {
+        let created = self.missing_paths();
+
+        let content = self.lines.join("\n");
+        let path = self.resolve();
+
+        if let Some(backup) = &self.backup {
+            self.back_up(&path, backup)?;
+        }
+
+        let result = match self.path.parent() {
+            Some(parent) => std::fs::create_dir_all(parent),
+            None => Ok(()),
+        }
+        .and_then(|_| self.write_content(&path, content.as_bytes()))
+        .and_then(|strategy| self.give_to_owner(&created).map(|_| strategy));
+
+        let strategy = result.map_err(|e| self.write_error(e))?;
+        if let Some(WriteHook(on_write)) = &self.on_write {
+            on_write(&WrittenFile {
+                path: self.path.clone(),
+                target: path,
+                strategy,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the file and its parent directories that don't exist yet, if
+    /// they are to be given to an owner
+    ///
+    /// They are collected before writing, as they exist afterwards.
+    fn missing_paths(&self) -> Vec<&Path> {
+        match self.owner {
+            Some(_) => self.path.ancestors().take_while(|p| !p.exists()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Gives the given paths to the owner of the file, if any
+    fn give_to_owner(&self, paths: &[&Path]) -> std::io::Result<()> {
+        match self.owner {
+            Some((uid, gid)) => paths
+                .iter()
+                .try_for_each(|path| std::os::unix::fs::chown(path, Some(uid), Some(gid))),
+            None => Ok(()),
+        }
+    }
+
+    /// Creates the file, which must not exist, with the given permissions
+    /// from the start, so that its content is never more accessible
+    ///
+    /// The file and the parent directories created are given to the owner of
+    /// the file, if any.
+    fn create(&self, permissions: Permissions) -> Result<(), PathmanError> {
+        let created = self.missing_paths();
+
+        let result = match self.path.parent() {
+            Some(parent) => std::fs::create_dir_all(parent),
+            None => Ok(()),
+        }
+        .and_then(|_| {
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(permissions.mode())
+                .open(&self.path)
+        })
+        .and_then(|mut file| {
+            // The mode given on creation is restricted by the umask
+            let result = file
+                .set_permissions(permissions)
+                .and_then(|_| file.write_all(self.lines.join("\n").as_bytes()))
+                .and_then(|_| self.give_to_owner(&created));
+
+            if result.is_err() {
+                let _ = std::fs::remove_file(&self.path);
+            }
+            result
+        });
+
+        result.map_err(|e| self.write_error(e))
+    }
+
+    /// Copies the file at the given path to a new backup along with its
+    /// permissions, or records that it doesn't exist, and deletes the oldest
+    /// backups
+    fn back_up(&self, path: &Path, backup: &Backup) -> Result<(), PathmanError> {
+        let (copy_path, content) = match std::fs::read_to_string(path) {
+            Ok(content) => (backup.create_path(path), Some(content)),
+            Err(e) if e.kind() == ErrorKind::NotFound => (backup.create_absent_path(path), None),
+            Err(_) => {
+                return Err(UnableToReadShellConfigFile(
+                    path.to_string_lossy().to_string(),
+                ));
+            }
+        };
+
+        // The backup may hold secrets, so it shouldn't be more accessible
+        // than the file itself
+        let permissions = match content {
+            Some(_) => std::fs::metadata(path)
+                .map(|metadata| metadata.permissions())
+                .map_err(|e| self.write_error(e))?,
+            None => Permissions::from_mode(0o600),
+        };
+
+        let copy = ConfigFile {
+            path: copy_path,
+            lines: match &content {
+                Some(content) => content.split('\n').map(String::from).collect(),
+                None => Vec::new(),
+            },
+            system: self.system,
+            owner: self.owner,
+            on_write: None,
+            backup: None,
+        };
+        copy.create(permissions)?;
+
+        backup.prune(path);
+
+        Ok(())
+    }
+
+    /// Restores the file from its last backup, and deletes that backup, so
+    /// that restoring it again rolls back the change before
+    pub fn restore(&mut self, backup: &Backup) -> Result<(), PathmanError> {
+        let path = self.resolve();
+
+        let Some(last) = backup.list(&path).pop() else {
+            return Err(NoBackup(self.path.to_string_lossy().to_string()));
+        };
+
+        // The file didn't exist before the change, so it's deleted again
+        if Backup::is_absent(&last) {
+            match std::fs::remove_file(&path) {
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(self.write_error(e)),
+            }
+
+            return std::fs::remove_file(&last).map_err(|e| self.write_error(e));
+        }
+
+        let content = match std::fs::read_to_string(&last) {
+            Ok(content) => content,
+            Err(_) => {
+                return Err(UnableToReadShellConfigFile(
+                    last.to_string_lossy().to_string(),
+                ));
+            }
+        };
+
+        self.lines = content.split('\n').map(String::from).collect();
+        self.write()?;
+
+        std::fs::remove_file(&last).map_err(|e| self.write_error(e))
+    }
+
+    /// Returns the file to write, following symbolic links
+    ///
+    /// Dotfiles managed by tools such as GNU Stow or chezmoi are often
+    /// symbolic links into a repository, whose target is edited rather than
+    /// replacing the link with a regular file.
+    fn resolve(&self) -> PathBuf {
+        match std::fs::symlink_metadata(&self.path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                std::fs::canonicalize(&self.path).unwrap_or_else(|_| self.path.clone())
+            }
+            _ => self.path.clone(),
+        }
+    }
+
+    /// Writes the content to the file, replacing it atomically when possible
+    /// and overwriting it in place otherwise
+    ///
+    /// Symbolic links that couldn't be resolved are written in place, as
+    /// replacing them would replace the link rather than its target.
+    fn write_content(&self, path: &Path, content: &[u8]) -> std::io::Result<WriteStrategy> {
+        let is_symlink =
+            std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink());
+
+        if !is_symlink && self.replace(path, content).is_ok() {
+            return Ok(WriteStrategy::Atomic);
+        }
+
+        std::fs::write(path, content).map(|_| WriteStrategy::InPlace)
+    }
+
+    /// Writes the content to a temporary file in the same directory, and
+    /// renames it over the file
+    ///
+    /// The permissions, the ownership and the extended attributes of the
+    /// file are carried over to the temporary file, and the whole operation
+    /// fails if any of them can't be, so that replacing the file never
+    /// changes them.
+    fn replace(&self, path: &Path, content: &[u8]) -> std::io::Result<()> {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(ErrorKind::InvalidInput.into());
+        };
+        let temp = parent.join(format!(
+            ".{}.{}.tmp",
+            name.to_string_lossy(),
+            std::process::id()
+        ));
+
+        let metadata = match std::fs::metadata(path) {
+            Ok(metadata) => Some(metadata),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+
+        // Replacing the file only requires write access to the directory, so
+        // check that the file itself is writable, as it would be in place
+        if metadata.is_some() {
+            OpenOptions::new().write(true).open(path)?;
+        }
+
+        let result = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)
+            .and_then(|mut file| {
+                file.write_all(content)?;
+
+                if let Some(metadata) = &metadata {
+                    // Changing the owner may clear the setuid and setgid bits,
+                    // so the permissions are set afterwards
+                    std::os::unix::fs::fchown(&file, Some(metadata.uid()), Some(metadata.gid()))?;
+                    file.set_permissions(metadata.permissions())?;
+                    copy_xattrs(path, &temp)?;
+                }
+
+                file.sync_all()
+            })
+            .and_then(|_| std::fs::rename(&temp, path));
+
+        match result {
+            Ok(_) => {
+                // Persist the rename itself, which isn't critical as the file
+                // is complete either way
+                let _ = File::open(parent).and_then(|dir| dir.sync_all());
+                Ok(())
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&temp);
+                Err(e)
+            }
+        }
+    }
+
+    /// Deletes the configuration file, if it exists
+    pub fn delete(&self) -> Result<(), PathmanError> {
+        if let Some(backup) = &self.backup
+            && std::fs::symlink_metadata(&self.path).is_ok()
+        {
+            self.back_up(&self.path, backup)?;
+        }
+
+        match std::fs::remove_file(&self.path) {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(self.write_error(e)),
+        }
+    }
+
+    /// Converts an error that occurred while modifying the file
+    fn write_error(&self, error: std::io::Error) -> PathmanError {
+        let path = self.path.to_string_lossy().to_string();
+
+        match error.kind() {
+            // System-wide files usually require root privileges, which is
+            // worth telling apart from other failures
+            ErrorKind::PermissionDenied if self.system => InsufficientPermissions(path),
+            _ => UnableToWriteShellConfigFile(path),
+        }
+    }
+}
+
+/// Copies the extended attributes of the source file to the target file
+///
+/// File systems that don't support extended attributes have none to copy.
+fn copy_xattrs(source: &Path, target: &Path) -> std::io::Result<()> {
+    let names = match xattr::list(source) {
+        Ok(names) => names,
+        Err(e) if e.kind() == ErrorKind::Unsupported => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    for name in names {
+        if let Some(value) = xattr::get(source, &name)? {
+            xattr::set(target, &name, &value)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,310 @@
+use crate::error::PathmanError;
+use crate::error::PathmanError::UnableToConvertPathToString;
+use crate::expand::expand;
+use crate::options::UpdateOptions;
+use crate::path_list::PathList;
+use crate::platform::unix::config::ConfigFile;
+use crate::platform::{Position, UpdateType};
+use crate::status::Location;
+use std::path::Path;
+
+/// The PATH used by pam_env when `/etc/environment` doesn't set it, which
+/// is kept when adding the first entry.
+const DEFAULT_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// A line of `/etc/environment` assigning a value to a variable
+struct Assignment {
+    /// The index of the line in the file
+    line: usize,
+
+    /// The beginning of the line, up to and including the `=` sign
+    prefix: String,
+
+    /// The quote surrounding the value, if any
+    quote: Option<char>,
+
+    /// The value, without its quotes
+    value: String,
+}
+
+/// Opens `/etc/environment` under the root directory of the options
+fn open(options: &UpdateOptions) -> Result<ConfigFile, PathmanError> {
+    Ok(ConfigFile::open(options.root.join("etc/environment"))?
+        .system(true)
+        .on_write(options.on_write.clone())
+        .backup(options.backup_settings()?))
+}
+
+/// Expands `~` and environment variables in the given path
+///
+/// pam_env doesn't expand the values of `/etc/environment`, so the paths are
+/// always expanded and written literally.
+pub fn resolve_path(path: &Path, home: Option<&Path>) -> Result<String, PathmanError> {
+    match path.to_str() {
+        Some(p) => expand(p, home),
+        None => Err(UnableToConvertPathToString),
+    }
+}
+
+/// Parses the given line as an assignment to the given variable, using the
+/// `KEY=value` or `KEY="value"` syntax, optionally preceded by `export`
+fn parse_assignment(index: usize, line: &str, name: &str) -> Option<Assignment> {
+    let trimmed = line.trim_start();
+    let statement = trimmed.strip_prefix("export ").unwrap_or(trimmed);
+
+    let value = statement.strip_prefix(name)?.strip_prefix('=')?;
+    let prefix = &line[..line.len() - value.len()];
+
+    let quote = value
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '"' | '\'') && value.len() > 1 && value.ends_with(*c));
+    let value = match quote {
+        Some(_) => &value[1..value.len() - 1],
+        None => value.trim_end(),
+    };
+
+    Some(Assignment {
+        line: index,
+        prefix: prefix.to_string(),
+        quote,
+        value: value.to_string(),
+    })
+}
+
+/// Finds the lines assigning a value to the given variable
+fn find_assignments(config_file: &ConfigFile, name: &str) -> Vec<Assignment> {
+    config_file
+        .lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| parse_assignment(index, line, name))
+        .collect()
+}
+
+/// Writes the given value to the assignment, keeping its prefix and quotes,
+/// or adds a new assignment if there is none
+fn write_value(
+    config_file: &mut ConfigFile,
+    assignment: Option<&Assignment>,
+    name: &str,
+    value: &str,
+    comment: Option<&str>,
+) -> Result<(), PathmanError> {
+    match assignment {
+        Some(assignment) => {
+            let quote = assignment.quote.map(String::from).unwrap_or_default();
+            config_file.lines[assignment.line] =
+                format!("{}{quote}{value}{quote}", assignment.prefix);
+        }
+        None => config_file.push(comment, &format!("{name}=\"{value}\"")),
+    }
+
+    config_file.write()
+}
+
+/// Parses the value of the variable of the options into a list, starting
+/// from the default PATH if the variable is not set
+fn parse_list(
+    assignment: Option<&Assignment>,
+    home: Option<&Path>,
+    options: &UpdateOptions,
+) -> PathList {
+    let value = match (assignment, options.variable.as_str()) {
+        (Some(assignment), _) => assignment.value.as_str(),
+        (None, "PATH") => DEFAULT_PATH,
+        (None, _) => "",
+    };
+
+    PathList::parse(value, ':')
+        .equivalence(options.equivalence)
+        .home(home)
+}
+
+/// Adds the paths to the variable of the options at the given position,
+/// returning the update type of each path in the order of the input.
+pub fn update(
+    paths: &[&Path],
+    position: &Position,
+    comment: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<Vec<UpdateType>, PathmanError> {
+    let home = options.find_home_dir()?;
+
+    let paths = paths
+        .iter()
+        .map(|path| resolve_path(path, home.as_deref()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let position = match position {
+        Position::Before { anchor, fallback } => {
+            Position::before(resolve_path(anchor, home.as_deref())?, *fallback)
+        }
+        Position::After { anchor, fallback } => {
+            Position::after(resolve_path(anchor, home.as_deref())?, *fallback)
+        }
+        position => position.clone(),
+    };
+
+    let mut config_file = open(options)?;
+
+    // The last assignment is the one in effect
+    let assignment = find_assignments(&config_file, &options.variable).pop();
+    let mut list = parse_list(assignment.as_ref(), home.as_deref(), options);
+
+    let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+    let update_types = list.update(&paths, &position, options.move_existing);
+
+    if update_types.iter().all(|t| *t == UpdateType::AlreadyInPath) {
+        return Ok(update_types);
+    }
+
+    write_value(
+        &mut config_file,
+        assignment.as_ref(),
+        &options.variable,
+        &list.to_string(),
+        comment,
+    )?;
+
+    Ok(update_types)
+}
+
+/// Finds the lines adding the path to the variable of the options
+pub fn locate(path: &Path, options: &UpdateOptions) -> Result<Vec<Location>, PathmanError> {
+    let home = options.find_home_dir()?;
+    let entry = resolve_path(path, home.as_deref())?;
+
+    let config_file = open(options)?;
+
+    Ok(find_assignments(&config_file, &options.variable)
+        .iter()
+        .filter(|a| parse_list(Some(a), home.as_deref(), options).contains(&entry))
+        .map(|a| Location::ConfigFile {
+            path: config_file.path.clone(),
+            line: a.line + 1,
+        })
+        .collect())
+}
+
+/// Removes the path from the variable of the options
+///
+/// The assignment is removed along with the given comment when no entry is
+/// left, unless the variable is the PATH.
+pub fn remove(
+    path: &Path,
+    comment: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<UpdateType, PathmanError> {
+    let home = options.find_home_dir()?;
+    let entry = resolve_path(path, home.as_deref())?;
+
+    let mut config_file = open(options)?;
+
+    let Some(assignment) = find_assignments(&config_file, &options.variable).pop() else {
+        return Ok(UpdateType::NotInPath);
+    };
+
+    let mut list = parse_list(Some(&assignment), home.as_deref(), options);
+    if !list.remove(&entry) {
+        return Ok(UpdateType::NotInPath);
+    }
+
+    if list.is_empty() && options.variable != "PATH" {
+        config_file.remove(&[assignment.line], comment);
+        config_file.write()?;
+    } else {
+        let value = list.to_string();
+        write_value(&mut config_file, Some(&assignment), "", &value, None)?;
+    }
+
+    Ok(UpdateType::Removed)
+}
+
+/// Replaces the old path with the new path in the variable of the options,
+/// prepending the new path if the old path is not present
+pub fn replace(
+    old: &Path,
+    new: &Path,
+    options: &UpdateOptions,
+) -> Result<UpdateType, PathmanError> {
+    let home = options.find_home_dir()?;
+    let old_entry = resolve_path(old, home.as_deref())?;
+    let new_entry = resolve_path(new, home.as_deref())?;
+
+    let mut config_file = open(options)?;
+
+    let assignment = find_assignments(&config_file, &options.variable).pop();
+    let mut list = parse_list(assignment.as_ref(), home.as_deref(), options);
+
+    if !list.contains(&old_entry) {
+        let mut update_types = update(&[new], &Position::Front, None, options)?;
+
+        return Ok(update_types.remove(0));
+    }
+
+    if options.equivalence.equivalent(&old_entry, &new_entry) {
+        return Ok(UpdateType::AlreadyInPath);
+    }
+
+    list.replace(&old_entry, &new_entry);
+
+    let value = list.to_string();
+    write_value(&mut config_file, assignment.as_ref(), "", &value, None)?;
+
+    Ok(UpdateType::Replaced)
+}
+
+/// Sets the given variable to the given value
+pub fn set_var(
+    name: &str,
+    value: &str,
+    comment: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<UpdateType, PathmanError> {
+    let value = match options.literal {
+        true => value.to_string(),
+        false => resolve_path(Path::new(value), options.find_home_dir()?.as_deref())?,
+    };
+
+    let mut config_file = open(options)?;
+
+    let unchanged = |current: &str| match options.literal {
+        true => current == value,
+        false => options.equivalence.equivalent(current, &value),
+    };
+
+    let assignment = find_assignments(&config_file, name).pop();
+    let update_type = match &assignment {
+        None => UpdateType::Success,
+        Some(a) if unchanged(&a.value) => return Ok(UpdateType::AlreadyInPath),
+        Some(_) => UpdateType::Replaced,
+    };
+
+    write_value(&mut config_file, assignment.as_ref(), name, &value, comment)?;
+
+    Ok(update_type)
+}
+
+/// Removes the assignments of the given variable, along with the given
+/// comment
+pub fn unset_var(
+    name: &str,
+    comment: Option<&str>,
+    options: &UpdateOptions,
+) -> Result<UpdateType, PathmanError> {
+    let mut config_file = open(options)?;
+
+    let lines: Vec<usize> = find_assignments(&config_file, name)
+        .iter()
+        .map(|a| a.line)
+        .collect();
+    if lines.is_empty() {
+        return Ok(UpdateType::NotInPath);
+    }
+
+    config_file.remove(&lines, comment);
+    config_file.write()?;
+
+    Ok(UpdateType::Removed)
+}
@@ -1,82 +1,993 @@
+mod config;
+mod environment;
+mod parser;
+mod paths_d;
 mod shell;
 
+use crate::activation::Activation;
+use crate::backup::Backup;
 use crate::error::PathmanError;
 use crate::error::PathmanError::{
-    UnableToCreateExportCommand, UnableToReadShellConfigFile, UnableToWriteShellConfigFile,
+    ManagedConfigFile, UnableToConvertPathToString, UnableToCreateExportCommand,
+    UnableToRemoveEntry, UnsupportedTarget,
 };
-use crate::platform::unix::shell::CurrentShell;
-use crate::platform::{PathUpdater, UpdateType};
+use crate::expand::{Expansion, expand, expand_variables};
+use crate::options::UpdateOptions;
+use crate::platform::unix::config::{ConfigFile, Match};
+use crate::platform::unix::parser::split_statements;
+use crate::platform::unix::shell::{CurrentShell, Shell};
+use crate::platform::{Fallback, PathUpdater, Position, UpdateType};
+use crate::status::Location;
+use crate::target::Target;
 use std::path::{Path, PathBuf};
 
 /// Path Updater for macOS
 pub struct UnixPathUpdater;
 
 impl UnixPathUpdater {
-    fn write_to_shell_config_file(
-        config_file_path: PathBuf,
-        export_line: &str,
+    /// Expands `~` and environment variables in the given path, unless the
+    /// expansion is deferred to the shell.
+    fn resolve_path(
+        shell: &CurrentShell,
+        path: &Path,
+        options: &UpdateOptions,
+    ) -> Result<PathBuf, PathmanError> {
+        match options.expansion {
+            Expansion::Immediate => match path.to_str() {
+                Some(p) => Ok(PathBuf::from(Self::expand_value(shell, p)?)),
+                None => Err(UnableToConvertPathToString),
+            },
+            Expansion::Deferred => Ok(path.to_path_buf()),
+        }
+    }
+
+    /// Expands `~` and environment variables in the given value
+    ///
+    /// System-wide files are read by every user, so the home directory is
+    /// kept as a leading `~` for them, which is written as `$HOME`.
+    fn expand_value(shell: &CurrentShell, value: &str) -> Result<String, PathmanError> {
+        match &shell.home {
+            Some(home) => expand(value, Some(home)),
+            None => expand_variables(value),
+        }
+    }
+
+    /// Opens the files of the target of the options, along with the shell
+    /// whose syntax each of them is written in.
+    fn open(options: &UpdateOptions) -> Result<Vec<(CurrentShell, ConfigFile)>, PathmanError> {
+        let config_files = match &options.target {
+            Target::ShellConfig => {
+                let shell = CurrentShell::detect(options)?;
+                let path = Self::check_managed(shell.config_file_path()?, options)?;
+                let config_file = ConfigFile::read(path)?.owner(Self::owner(options)?);
+
+                Ok(vec![(shell, config_file)])
+            }
+            Target::EnvironmentD { name } => {
+                let shell = CurrentShell::new(Shell::Systemd, options)?;
+                let path = Self::check_managed(shell.environment_d_path(name)?, options)?;
+                let config_file = ConfigFile::open(path)?.owner(Self::owner(options)?);
+
+                Ok(vec![(shell, config_file)])
+            }
+            // These targets aren't shell files, and are updated by the
+            // environment and paths_d modules instead
+            Target::Environment | Target::PathsD { .. } => Err(UnsupportedTarget),
+            Target::ProfileD { name } => {
+                let mut config_files = Vec::new();
+
+                for shell in [Shell::Bash, Shell::Fish] {
+                    let shell = CurrentShell::system(shell, options);
+                    let path = shell.system_config_path(name);
+                    config_files.push((shell, ConfigFile::open(path)?.system(true)));
+                }
+
+                Ok(config_files)
+            }
+            Target::Skeleton { dir } => {
+                let mut config_files = Vec::new();
+
+                // The skeleton directory stands for the home directory of the
+                // new users
+                for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+                    let shell = CurrentShell::new(shell, &options.clone().home(dir))?;
+                    let path = shell.preferred_config_file_path()?;
+                    config_files.push((shell, ConfigFile::open(path)?.system(true)));
+                }
+
+                Ok(config_files)
+            }
+        }?;
+
+        let backup = options.backup_settings()?;
+
+        Ok(config_files
+            .into_iter()
+            .map(|(shell, config_file)| {
+                let config_file = config_file
+                    .on_write(options.on_write.clone())
+                    .backup(backup.clone());
+
+                (shell, config_file)
+            })
+            .collect())
+    }
+
+    /// Returns whether the file is managed declaratively, as a symbolic link
+    /// into the Nix store of the root directory (e.g., by home-manager)
+    fn is_managed(path: &Path, options: &UpdateOptions) -> bool {
+        let store = options.root.join("nix/store");
+
+        match (std::fs::canonicalize(path), std::fs::canonicalize(store)) {
+            (Ok(path), Ok(store)) => path.starts_with(store),
+            _ => false,
+        }
+    }
+
+    /// Returns the given path, or an error if the file is managed
+    /// declaratively, before anything is written
+    fn check_managed(path: PathBuf, options: &UpdateOptions) -> Result<PathBuf, PathmanError> {
+        match Self::is_managed(&path, options) {
+            true => Err(ManagedConfigFile(path.to_string_lossy().to_string())),
+            false => Ok(path),
+        }
+    }
+
+    /// Returns the options targeting the managed fallback of the options when
+    /// the shell configuration file is managed declaratively, or the options
+    /// themselves otherwise.
+    fn redirect(options: &UpdateOptions) -> UpdateOptions {
+        let Some(fallback) = &options.managed_fallback else {
+            return options.clone();
+        };
+
+        let is_managed = options.target == Target::ShellConfig
+            && CurrentShell::detect(options)
+                .and_then(|shell| shell.config_file_path())
+                .is_ok_and(|path| Self::is_managed(&path, options));
+
+        match is_managed {
+            true => options.clone().target(fallback.clone()),
+            false => options.clone(),
+        }
+    }
+
+    /// Returns the UID and GID of the user of the options, who owns the files
+    /// created in their home directory, or `None` for the current user
+    fn owner(options: &UpdateOptions) -> Result<Option<(u32, u32)>, PathmanError> {
+        Ok(options.account()?.map(|account| (account.uid, account.gid)))
+    }
+
+    /// Combines the update types of the files of the target, reporting the
+    /// first change if any of the files was changed.
+    fn merge(update_types: &[UpdateType]) -> UpdateType {
+        let is_unchanged =
+            |t: &&UpdateType| matches!(t, UpdateType::AlreadyInPath | UpdateType::NotInPath);
+
+        match update_types.iter().find(|t| !is_unchanged(t)) {
+            Some(update_type) => *update_type,
+            None => update_types[0],
+        }
+    }
+
+    /// Adds the paths to the PATH at the given position in each file of the
+    /// target, returning the update type of each path in the order of the
+    /// input.
+    fn update(
+        paths: &[&Path],
+        position: &Position,
         comment: Option<&str>,
-    ) -> Result<UpdateType, PathmanError> {
-        // Read the existing content of the shell configuration file
-        let mut content = match std::fs::read_to_string(&config_file_path) {
-            Ok(content) => content,
-            Err(_) => {
-                return Err(UnableToReadShellConfigFile(
-                    config_file_path.to_string_lossy().to_string(),
-                ));
+        options: &UpdateOptions,
+    ) -> Result<Vec<UpdateType>, PathmanError> {
+        let options = &Self::redirect(options);
+
+        match &options.target {
+            Target::Environment => return environment::update(paths, position, comment, options),
+            Target::PathsD { name } => return paths_d::update(name, paths, position, options),
+            _ => {}
+        }
+
+        let mut update_types: Vec<Vec<UpdateType>> = vec![Vec::new(); paths.len()];
+
+        for (shell, mut config_file) in Self::open(options)? {
+            let file_update_types =
+                Self::update_file(&shell, &mut config_file, paths, position, comment, options)?;
+
+            for (index, update_type) in file_update_types.into_iter().enumerate() {
+                update_types[index].push(update_type);
+            }
+        }
+
+        Ok(update_types.iter().map(|t| Self::merge(t)).collect())
+    }
+
+    /// Builds the line adding the paths to the PATH at the given position and
+    /// writes it to the given configuration file.
+    ///
+    /// The paths that are not in the PATH yet are added by a single line,
+    /// preceded by the comment. The update type of each path is returned in
+    /// the order of the input.
+    fn update_file(
+        shell: &CurrentShell,
+        config_file: &mut ConfigFile,
+        paths: &[&Path],
+        position: &Position,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<Vec<UpdateType>, PathmanError> {
+        let paths = paths
+            .iter()
+            .map(|path| Self::resolve_path(shell, path, options))
+            .collect::<Result<Vec<_>, _>>()?;
+        let position = Self::resolve_position(shell, position, options)?;
+
+        let mut update_types = Vec::new();
+        let mut added: Vec<&Path> = Vec::new();
+        let mut seen = Vec::new();
+        let mut moved = Vec::new();
+
+        for path in &paths {
+            let entry = match path.to_str() {
+                Some(p) => p,
+                None => return Err(UnableToConvertPathToString),
+            };
+
+            // The same path may be given more than once
+            let normalized = options.equivalence.normalize(entry, shell.home.as_deref());
+            if seen.contains(&normalized) {
+                update_types.push(UpdateType::AlreadyInPath);
+                continue;
+            }
+            seen.push(normalized);
+
+            // Check if the path is already added to the PATH by the
+            // configuration file, regardless of its position
+            let matches = config_file.find(shell, entry, options);
+
+            // An entry added next to an anchor is at the requested position if
+            // it was added by the same line
+            let export_line = Self::get_export_line(shell, &[path.as_path()], &position, options)?;
+            let at_position = |m: &Match| match position {
+                Position::Front | Position::Back => m.position == position,
+                _ => config_file.lines[m.line] == export_line,
+            };
+
+            if matches.is_empty() {
+                update_types.push(UpdateType::Success);
+            } else if !options.move_existing || matches.iter().any(|m| at_position(m) || m.shared) {
+                // Lines adding other entries as well are left untouched
+                update_types.push(UpdateType::AlreadyInPath);
+                continue;
+            } else {
+                moved.extend(matches);
+                update_types.push(UpdateType::Moved);
             }
+
+            added.push(path);
+        }
+
+        if added.is_empty() {
+            return Ok(update_types);
+        }
+
+        let export_lines = Self::get_export_lines(shell, &added, &position, options)?;
+
+        Self::remove_matches(shell, config_file, &moved, comment, options)?;
+
+        // Write the export lines to the shell configuration file
+        config_file.push(comment, &export_lines[0]);
+        for export_line in &export_lines[1..] {
+            config_file.push(None, export_line);
+        }
+        config_file.write()?;
+
+        Ok(update_types)
+    }
+
+    /// Resolves the anchor of the given position for the given shell
+    fn resolve_position(
+        shell: &CurrentShell,
+        position: &Position,
+        options: &UpdateOptions,
+    ) -> Result<Position, PathmanError> {
+        Ok(match position {
+            Position::Before { anchor, fallback } => Position::Before {
+                anchor: Self::resolve_path(shell, anchor, options)?,
+                fallback: *fallback,
+            },
+            Position::After { anchor, fallback } => Position::After {
+                anchor: Self::resolve_path(shell, anchor, options)?,
+                fallback: *fallback,
+            },
+            position => position.clone(),
+        })
+    }
+
+    /// Builds the lines adding the paths to the PATH at the given position.
+    ///
+    /// Each path needs its own line when it is guarded by a check for its
+    /// existence. Lines inserting at the front or after the anchor are
+    /// written in reverse, so that the paths keep their order in the PATH.
+    fn get_export_lines(
+        shell: &CurrentShell,
+        paths: &[&Path],
+        position: &Position,
+        options: &UpdateOptions,
+    ) -> Result<Vec<String>, PathmanError> {
+        if !options.only_if_exists {
+            return Ok(vec![Self::get_export_line(
+                shell, paths, position, options,
+            )?]);
+        }
+
+        let mut paths = paths.to_vec();
+        if matches!(position, Position::Front | Position::After { .. }) {
+            paths.reverse();
+        }
+
+        paths
+            .iter()
+            .map(|path| Self::get_export_line(shell, &[path], position, options))
+            .collect()
+    }
+
+    /// Builds the line adding the paths to the PATH at the given position,
+    /// guarded by a check for the existence of the directory if requested,
+    /// and preceded by the initialization of the variable if needed.
+    fn get_export_line(
+        shell: &CurrentShell,
+        paths: &[&Path],
+        position: &Position,
+        options: &UpdateOptions,
+    ) -> Result<String, PathmanError> {
+        let export_command = match shell.get_command(paths, position, options) {
+            Ok(command) => command,
+            Err(_) => return Err(UnableToCreateExportCommand),
+        };
+
+        let export_line = match options.only_if_exists {
+            true => match shell.get_guarded_command(paths[0], &export_command, options) {
+                Ok(line) => line,
+                Err(_) => return Err(UnableToCreateExportCommand),
+            },
+            false => export_command,
+        };
+
+        match shell.get_init_command(options) {
+            Some(init_command) => Ok(format!("{init_command}; {export_line}")),
+            None => Ok(export_line),
+        }
+    }
+
+    /// Returns the inputs that may have produced the given value, as written
+    /// in a configuration file, along with the options they were written with
+    ///
+    /// The value was either expanded and written relative to the home
+    /// directory or not, or written with its variables preserved.
+    fn written_forms(
+        shell: &CurrentShell,
+        value: &str,
+        options: &UpdateOptions,
+    ) -> Vec<(String, UpdateOptions)> {
+        let mut forms = Vec::new();
+
+        if let Ok(expanded) = Self::expand_value(shell, value) {
+            for home_relative in [true, false] {
+                let options = options
+                    .clone()
+                    .expansion(Expansion::Immediate)
+                    .home_relative(home_relative);
+                forms.push((expanded.clone(), options));
+            }
+        }
+        forms.push((
+            value.to_string(),
+            options.clone().expansion(Expansion::Deferred),
+        ));
+
+        forms
+    }
+
+    /// Returns whether the line is one of the lines written to add the entry,
+    /// as written in the line, which can be removed without breaking the rest
+    /// of the file.
+    fn is_generated(
+        shell: &CurrentShell,
+        line: &str,
+        entry: &str,
+        options: &UpdateOptions,
+    ) -> bool {
+        // The anchor of a splice command is unknown, so the lines are built
+        // with a placeholder standing for the anchor found in the line
+        const PLACEHOLDER: &str = "\0";
+        let anchor = shell.splice_anchor(line);
+
+        let mut positions = vec![Position::Front, Position::Back];
+        if anchor.is_some() {
+            for fallback in [Fallback::Front, Fallback::Back] {
+                positions.push(Position::before(PLACEHOLDER, fallback));
+                positions.push(Position::after(PLACEHOLDER, fallback));
+            }
+        }
+
+        let is_line = |generated: String| match anchor {
+            Some(anchor) => generated.replace(PLACEHOLDER, anchor) == line.trim_end(),
+            None => generated == line.trim_end(),
         };
 
-        // Check if the export line is already present in the content
-        if content.contains(export_line) {
+        Self::written_forms(shell, entry, options)
+            .into_iter()
+            .any(|(path, options)| {
+                [false, true].into_iter().any(|only_if_exists| {
+                    let options = options.clone().only_if_exists(only_if_exists);
+
+                    positions.iter().any(|position| {
+                        Self::get_export_line(shell, &[Path::new(&path)], position, &options)
+                            .is_ok_and(is_line)
+                    })
+                })
+            })
+    }
+
+    /// Removes the entries of the matches from the given configuration file.
+    ///
+    /// Only the lines written to add an entry are removed, along with the
+    /// given comment. The other lines may be part of a compound command (e.g.,
+    /// an `if` block), so they are kept without the entry instead, and an
+    /// error is returned if the variable would be left empty. Fish accepts
+    /// empty blocks, so lines consisting of a single command only adding the
+    /// entry are removed as well.
+    fn remove_matches(
+        shell: &CurrentShell,
+        config_file: &mut ConfigFile,
+        matches: &[Match],
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<(), PathmanError> {
+        let mut lines = Vec::new();
+
+        for m in matches {
+            let line = &config_file.lines[m.line];
+            let is_single_command = shell.shell == Shell::Fish && split_statements(line).len() == 1;
+
+            if !m.shared
+                && (is_single_command || Self::is_generated(shell, line, &m.value, options))
+            {
+                lines.push(m.line);
+                continue;
+            }
+
+            let removed = shell.remove_entry(line, &m.value);
+            if shell
+                .parse_assignment(&removed, &options.variable)
+                .is_some_and(|value| value.is_empty())
+            {
+                return Err(UnableToRemoveEntry(format!(
+                    "{}:{}",
+                    config_file.path.display(),
+                    m.line + 1
+                )));
+            }
+            config_file.lines[m.line] = removed;
+        }
+
+        config_file.remove(&lines, comment);
+
+        Ok(())
+    }
+
+    /// Adds a single path to the PATH at the given position.
+    fn update_one(
+        path: &Path,
+        position: &Position,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        let mut update_types = Self::update(&[path], position, comment, options)?;
+
+        Ok(update_types.remove(0))
+    }
+
+    /// Removes the path from the given configuration file.
+    fn remove_file(
+        shell: &CurrentShell,
+        config_file: &mut ConfigFile,
+        path: &Path,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        let path = Self::resolve_path(shell, path, options)?;
+        let entry = match path.to_str() {
+            Some(p) => p,
+            None => return Err(UnableToConvertPathToString),
+        };
+
+        let matches = config_file.find(shell, entry, options);
+        if matches.is_empty() {
+            return Ok(UpdateType::NotInPath);
+        }
+
+        Self::remove_matches(shell, config_file, &matches, comment, options)?;
+        config_file.write()?;
+
+        Ok(UpdateType::Removed)
+    }
+
+    /// Sets the variable to the value in the given configuration file.
+    fn set_var_file(
+        shell: &CurrentShell,
+        config_file: &mut ConfigFile,
+        name: &str,
+        value: &str,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        let value = match options.literal {
+            true => PathBuf::from(value),
+            false => Self::resolve_path(shell, Path::new(value), options)?,
+        };
+        let value = match value.to_str() {
+            Some(v) => v,
+            None => return Err(UnableToConvertPathToString),
+        };
+
+        let set_line = match shell.get_set_command(name, value, options) {
+            Ok(command) => command,
+            Err(_) => return Err(UnableToCreateExportCommand),
+        };
+
+        // The last assignment is the one in effect
+        let Some(assignment) = config_file.find_assignments(shell, name).pop() else {
+            config_file.push(comment, &set_line);
+            config_file.write()?;
+
+            return Ok(UpdateType::Success);
+        };
+
+        let home = shell.home.as_deref();
+        let unchanged = match options.literal {
+            true => assignment.value == value,
+            false => {
+                options.equivalence.normalize(&assignment.value, home)
+                    == options.equivalence.normalize(value, home)
+            }
+        };
+        if unchanged {
             return Ok(UpdateType::AlreadyInPath);
         }
 
-        let comment = match comment {
-            Some(comment) => format!("\n# {comment}"),
-            None => String::new(),
+        // Update the value in place, preserving the rest of the line and its
+        // comment, or replace the whole line if the value cannot be found
+        let line = &config_file.lines[assignment.line];
+        let replaced = match assignment.value.is_empty() {
+            true => line.clone(),
+            false => match shell.format_value(value, options) {
+                Ok(replacement) => shell.replace_entry(line, &assignment.value, &replacement),
+                Err(_) => return Err(UnableToCreateExportCommand),
+            },
         };
+        config_file.lines[assignment.line] = match replaced == *line {
+            true => set_line,
+            false => replaced,
+        };
+
+        config_file.write()?;
+
+        Ok(UpdateType::Replaced)
+    }
+
+    /// Returns whether the line is the line written to set the variable to
+    /// the given value, as read from the line, which can be removed without
+    /// breaking the rest of the file.
+    fn is_set_line(
+        shell: &CurrentShell,
+        line: &str,
+        name: &str,
+        value: &str,
+        options: &UpdateOptions,
+    ) -> bool {
+        // The value was either written literally or like a path
+        let mut candidates = vec![(value.to_string(), options.clone().literal(true))];
+        candidates.extend(
+            Self::written_forms(shell, value, options)
+                .into_iter()
+                .map(|(value, options)| (value, options.literal(false))),
+        );
+
+        candidates.iter().any(|(value, options)| {
+            shell
+                .get_set_command(name, value, options)
+                .is_ok_and(|generated| generated == line.trim_end())
+        })
+    }
 
-        // Prepend the export line to the content
-        content.push_str(&format!("{comment}\n{export_line}"));
+    /// Removes the assignments of the variable from the given configuration
+    /// file.
+    ///
+    /// Lines that were not written to set the variable may be part of a
+    /// compound command (e.g., an `if` block), so an error is returned
+    /// instead of removing them.
+    fn unset_var_file(
+        shell: &CurrentShell,
+        config_file: &mut ConfigFile,
+        name: &str,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        let assignments = config_file.find_assignments(shell, name);
+        if assignments.is_empty() {
+            return Ok(UpdateType::NotInPath);
+        }
 
-        // Write the content back to the shell configuration file
-        if std::fs::write(&config_file_path, content).is_err() {
-            return Err(UnableToWriteShellConfigFile(
-                config_file_path.to_string_lossy().to_string(),
-            ));
+        let mut lines = Vec::new();
+        for assignment in &assignments {
+            let line = &config_file.lines[assignment.line];
+            if !Self::is_set_line(shell, line, name, &assignment.value, options) {
+                return Err(UnableToRemoveEntry(format!(
+                    "{}:{}",
+                    config_file.path.display(),
+                    assignment.line + 1
+                )));
+            }
+            lines.push(assignment.line);
         }
 
-        Ok(UpdateType::Success)
+        config_file.remove(&lines, comment);
+        config_file.write()?;
+
+        Ok(UpdateType::Removed)
+    }
+
+    /// Replaces the old path with the new path in the given configuration
+    /// file.
+    fn replace_file(
+        shell: &CurrentShell,
+        config_file: &mut ConfigFile,
+        old: &Path,
+        new: &Path,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        let old = Self::resolve_path(shell, old, options)?;
+        let new = Self::resolve_path(shell, new, options)?;
+
+        let (old_entry, new_entry) = match (old.to_str(), new.to_str()) {
+            (Some(old), Some(new)) => (old, new),
+            _ => return Err(UnableToConvertPathToString),
+        };
+
+        let matches = config_file.find(shell, old_entry, options);
+        if matches.is_empty() {
+            let mut update_types =
+                Self::update_file(shell, config_file, &[&new], &Position::Front, None, options)?;
+
+            return Ok(update_types.remove(0));
+        }
+
+        let home = shell.home.as_deref();
+        if options.equivalence.normalize(old_entry, home)
+            == options.equivalence.normalize(new_entry, home)
+        {
+            return Ok(UpdateType::AlreadyInPath);
+        }
+
+        if config_file.find(shell, new_entry, options).is_empty() {
+            // Replace the old path in place, preserving its position and its
+            // comment
+            let replacement = match shell.format_path(&new, options) {
+                Ok(replacement) => replacement,
+                Err(_) => return Err(UnableToCreateExportCommand),
+            };
+
+            for m in &matches {
+                let line = &config_file.lines[m.line];
+                config_file.lines[m.line] = shell.replace_entry(line, &m.value, &replacement);
+            }
+        } else {
+            // The new path is already present, so the old path is only removed,
+            // leaving lines adding other entries as well untouched
+            let matches: Vec<Match> = matches.into_iter().filter(|m| !m.shared).collect();
+            if matches.is_empty() {
+                return Ok(UpdateType::AlreadyInPath);
+            }
+
+            Self::remove_matches(shell, config_file, &matches, None, options)?;
+        }
+
+        config_file.write()?;
+
+        Ok(UpdateType::Replaced)
     }
 }
 
 impl PathUpdater for UnixPathUpdater {
-    fn prepend<P: AsRef<Path>>(path: P, comment: Option<&str>) -> Result<UpdateType, PathmanError> {
-        // Retrieve the path to shell config file
-        let shell = CurrentShell::detect()?;
+    fn prepend<P: AsRef<Path>>(
+        path: P,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        Self::update_one(path.as_ref(), &Position::Front, comment, options)
+    }
 
-        // Prepare the path export line
-        let export_command = match shell.get_prepend_command(path) {
-            Ok(line) => line,
-            Err(_) => return Err(UnableToCreateExportCommand),
+    fn append<P: AsRef<Path>>(
+        path: P,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        Self::update_one(path.as_ref(), &Position::Back, comment, options)
+    }
+
+    fn insert<P: AsRef<Path>>(
+        path: P,
+        position: &Position,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        Self::update_one(path.as_ref(), position, comment, options)
+    }
+
+    fn insert_all<P: AsRef<Path>>(
+        paths: &[P],
+        position: &Position,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<Vec<UpdateType>, PathmanError> {
+        let paths: Vec<&Path> = paths.iter().map(AsRef::as_ref).collect();
+
+        Self::update(&paths, position, comment, options)
+    }
+
+    fn locate<P: AsRef<Path>>(
+        path: P,
+        options: &UpdateOptions,
+    ) -> Result<Vec<Location>, PathmanError> {
+        match &options.target {
+            Target::Environment => return environment::locate(path.as_ref(), options),
+            Target::PathsD { name } => return paths_d::locate(name, path.as_ref(), options),
+            _ => {}
+        }
+
+        // The path is searched for in the configuration files of every shell,
+        // not only the current one, unless another target is used
+        let config_files = match options.target {
+            Target::ShellConfig => {
+                let mut config_files = Vec::new();
+                for shell in CurrentShell::all(options)? {
+                    for config_file_path in shell.config_file_paths() {
+                        let config_file = ConfigFile::read(config_file_path)?;
+                        config_files.push((shell.clone(), config_file));
+                    }
+                }
+
+                config_files
+            }
+            _ => Self::open(options)?,
+        };
+
+        let mut locations = Vec::new();
+
+        for (shell, config_file) in config_files {
+            let path = Self::resolve_path(&shell, path.as_ref(), options)?;
+            let entry = match path.to_str() {
+                Some(p) => p,
+                None => return Err(UnableToConvertPathToString),
+            };
+
+            let mut lines: Vec<usize> = config_file
+                .find(&shell, entry, options)
+                .iter()
+                .map(|m| m.line)
+                .collect();
+            lines.dedup();
+
+            locations.extend(lines.into_iter().map(|line| Location::ConfigFile {
+                path: config_file.path.clone(),
+                line: line + 1,
+            }));
+        }
+
+        Ok(locations)
+    }
+
+    fn snippet<P: AsRef<Path>>(
+        paths: &[P],
+        position: &Position,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<String, PathmanError> {
+        let shell = CurrentShell::detect(options)?;
+
+        let paths = paths
+            .iter()
+            .map(|path| Self::resolve_path(&shell, path.as_ref(), options))
+            .collect::<Result<Vec<_>, _>>()?;
+        let paths: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+        let position = Self::resolve_position(&shell, position, options)?;
+
+        let mut lines: Vec<String> = comment.map(|c| format!("# {c}")).into_iter().collect();
+        lines.extend(Self::get_export_lines(&shell, &paths, &position, options)?);
+
+        Ok(lines.join("\n"))
+    }
+
+    fn restore<P: AsRef<Path>>(file: P, options: &UpdateOptions) -> Result<(), PathmanError> {
+        // The backups are looked up with the default settings when the
+        // options don't enable them
+        let options = match options.backup {
+            Some(_) => options.clone(),
+            None => options.clone().backup(Backup::new()),
         };
+        let backup = options.backup_settings()?.unwrap_or_default();
+
+        let mut config_file =
+            ConfigFile::open(file.as_ref().to_path_buf())?.on_write(options.on_write.clone());
 
-        // Write the export line to the shell configuration file
-        Self::write_to_shell_config_file(shell.config_file_path()?, &export_command, comment)
+        config_file.restore(&backup)
     }
 
-    fn append<P: AsRef<Path>>(path: P, comment: Option<&str>) -> Result<UpdateType, PathmanError> {
-        // Retrieve the path to shell config file
-        let shell = CurrentShell::detect()?;
+    fn activation(options: &UpdateOptions) -> Result<Vec<Activation>, PathmanError> {
+        let options = &Self::redirect(options);
 
-        // Prepare the path export line
-        let export_command = match shell.get_append_command(path) {
-            Ok(line) => line,
-            Err(_) => return Err(UnableToCreateExportCommand),
+        // The files of another system, and those copied to the home directory
+        // of new users, are never read by the current session
+        if options.is_staged() || matches!(options.target, Target::Skeleton { .. }) {
+            return Ok(Vec::new());
+        }
+
+        let shell = CurrentShell::detect(options)?;
+
+        // The files may belong to another home directory than the one of the
+        // session, such as that of the user who invoked sudo, in which case
+        // they are referred to by their absolute path
+        let mut session = shell.clone();
+        if session.home != home::home_dir() {
+            session.home = None;
+        }
+
+        let command = match &options.target {
+            Target::ShellConfig => Some(session.get_source_command(&shell.config_file_path()?)),
+            Target::ProfileD { name } => {
+                // Fish reads its own profile script, and the other shells the
+                // POSIX one
+                let file_shell = match shell.shell {
+                    Shell::Fish => Shell::Fish,
+                    _ => Shell::Bash,
+                };
+                let file = CurrentShell::system(file_shell, options).system_config_path(name);
+                Some(session.get_source_command(&file))
+            }
+            Target::EnvironmentD { name } => {
+                let file = CurrentShell::new(Shell::Systemd, options)?.environment_d_path(name)?;
+                session.get_import_command(&file)
+            }
+            Target::Environment => session.get_import_command(Path::new("/etc/environment")),
+            Target::PathsD { .. } => session.get_path_helper_command(),
+            Target::Skeleton { .. } => None,
         };
 
-        // Write the export line to the shell configuration file
-        Self::write_to_shell_config_file(shell.config_file_path()?, &export_command, comment)
+        Ok(command
+            .map(|command| Activation {
+                shell: shell.name().to_string(),
+                command,
+            })
+            .into_iter()
+            .collect())
+    }
+
+    fn remove<P: AsRef<Path>>(
+        path: P,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        let options = &Self::redirect(options);
+
+        match &options.target {
+            Target::Environment => return environment::remove(path.as_ref(), comment, options),
+            Target::PathsD { name } => return paths_d::remove(name, path.as_ref(), options),
+            _ => {}
+        }
+
+        let mut update_types = Vec::new();
+
+        for (shell, mut config_file) in Self::open(options)? {
+            update_types.push(Self::remove_file(
+                &shell,
+                &mut config_file,
+                path.as_ref(),
+                comment,
+                options,
+            )?);
+        }
+
+        Ok(Self::merge(&update_types))
+    }
+
+    fn set_var(
+        name: &str,
+        value: &str,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        let options = &Self::redirect(options);
+
+        match &options.target {
+            Target::Environment => return environment::set_var(name, value, comment, options),
+            Target::PathsD { .. } => return Err(UnsupportedTarget),
+            _ => {}
+        }
+
+        let mut update_types = Vec::new();
+
+        for (shell, mut config_file) in Self::open(options)? {
+            update_types.push(Self::set_var_file(
+                &shell,
+                &mut config_file,
+                name,
+                value,
+                comment,
+                options,
+            )?);
+        }
+
+        Ok(Self::merge(&update_types))
+    }
+
+    fn unset_var(
+        name: &str,
+        comment: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        let options = &Self::redirect(options);
+
+        match &options.target {
+            Target::Environment => return environment::unset_var(name, comment, options),
+            Target::PathsD { .. } => return Err(UnsupportedTarget),
+            _ => {}
+        }
+
+        let mut update_types = Vec::new();
+
+        for (shell, mut config_file) in Self::open(options)? {
+            update_types.push(Self::unset_var_file(
+                &shell,
+                &mut config_file,
+                name,
+                comment,
+                options,
+            )?);
+        }
+
+        Ok(Self::merge(&update_types))
+    }
+
+    fn replace<O: AsRef<Path>, N: AsRef<Path>>(
+        old: O,
+        new: N,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        let options = &Self::redirect(options);
+
+        match &options.target {
+            Target::Environment => {
+                return environment::replace(old.as_ref(), new.as_ref(), options);
+            }
+            Target::PathsD { name } => {
+                return paths_d::replace(name, old.as_ref(), new.as_ref(), options);
+            }
+            _ => {}
+        }
+
+        let mut update_types = Vec::new();
+
+        for (shell, mut config_file) in Self::open(options)? {
+            update_types.push(Self::replace_file(
+                &shell,
+                &mut config_file,
+                old.as_ref(),
+                new.as_ref(),
+                options,
+            )?);
+        }
+
+        Ok(Self::merge(&update_types))
     }
 }
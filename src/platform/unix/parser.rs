@@ -0,0 +1,86 @@
+/// Splits a line of shell code into statements made of words
+///
+/// Quotes are removed from the words and escaped characters are unescaped,
+/// but variables are left unexpanded. Statements are separated by unquoted
+/// `;`, `&&`, `||`, `&` and `|` operators. Parsing stops at an unquoted `#`
+/// starting a comment.
+///
+/// This is not a complete shell parser, but it understands the subset of the
+/// syntax shared by POSIX shells and fish that is used in PATH assignments.
+pub fn split_statements(line: &str) -> Vec<Vec<String>> {
+    let mut statements = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                for c in chars.by_ref() {
+                    if c == '\'' {
+                        break;
+                    }
+                    word.push(c);
+                }
+            }
+            '"' => {
+                in_word = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.peek() {
+                            Some(&next @ ('"' | '\\' | '$' | '`')) => {
+                                word.push(next);
+                                chars.next();
+                            }
+                            _ => word.push(c),
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                }
+            }
+            '#' if !in_word => break,
+            ';' | '&' | '|' => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+                if !words.is_empty() {
+                    statements.push(std::mem::take(&mut words));
+                }
+                // Skip the second character of `&&` and `||`
+                if chars.peek() == Some(&c) {
+                    chars.next();
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+
+    if in_word {
+        words.push(word);
+    }
+    if !words.is_empty() {
+        statements.push(words);
+    }
+
+    statements
+}
@@ -0,0 +1,167 @@
+use crate::error::PathmanError;
+use crate::error::PathmanError::UnsupportedTarget;
+use crate::options::UpdateOptions;
+use crate::path_list::PathList;
+use crate::platform::unix::config::ConfigFile;
+use crate::platform::unix::environment::resolve_path;
+use crate::platform::{Position, UpdateType};
+use crate::status::Location;
+use std::path::Path;
+
+/// Opens the `paths.d` file of the given name under the root directory of
+/// the options
+///
+/// `path_helper` reads the PATH entries from `/etc/paths.d`, and the MANPATH
+/// entries from `/etc/manpaths.d`. Other variables are not supported.
+fn open(name: &str, options: &UpdateOptions) -> Result<ConfigFile, PathmanError> {
+    let directory = match options.variable.as_str() {
+        "PATH" => "etc/paths.d",
+        "MANPATH" => "etc/manpaths.d",
+        _ => return Err(UnsupportedTarget),
+    };
+
+    Ok(ConfigFile::open(options.root.join(directory).join(name))?
+        .system(true)
+        .on_write(options.on_write.clone())
+        .backup(options.backup_settings()?))
+}
+
+/// Parses the entries of the file, one per line, ignoring blank lines
+fn parse_list(config_file: &ConfigFile, options: &UpdateOptions) -> Result<PathList, PathmanError> {
+    let lines: Vec<&str> = config_file
+        .lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    Ok(PathList::parse(&lines.join("\n"), '\n')
+        .equivalence(options.equivalence)
+        .home(options.find_home_dir()?.as_deref()))
+}
+
+/// Writes the entries back to the file, one per line, or deletes the file if
+/// no entry is left
+fn write_list(config_file: &mut ConfigFile, list: &PathList) -> Result<(), PathmanError> {
+    if list.is_empty() {
+        return config_file.delete();
+    }
+
+    config_file.lines = list.entries().to_vec();
+    config_file.lines.push(String::new());
+
+    config_file.write()
+}
+
+/// Adds the paths to the file at the given position, returning the update
+/// type of each path in the order of the input
+///
+/// `path_helper` doesn't expand variables, so the paths are always expanded
+/// and written literally.
+pub fn update(
+    name: &str,
+    paths: &[&Path],
+    position: &Position,
+    options: &UpdateOptions,
+) -> Result<Vec<UpdateType>, PathmanError> {
+    let home = options.find_home_dir()?;
+
+    let paths = paths
+        .iter()
+        .map(|path| resolve_path(path, home.as_deref()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let position = match position {
+        Position::Before { anchor, fallback } => {
+            Position::before(resolve_path(anchor, home.as_deref())?, *fallback)
+        }
+        Position::After { anchor, fallback } => {
+            Position::after(resolve_path(anchor, home.as_deref())?, *fallback)
+        }
+        position => position.clone(),
+    };
+
+    let mut config_file = open(name, options)?;
+    let mut list = parse_list(&config_file, options)?;
+
+    let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+    let update_types = list.update(&paths, &position, options.move_existing);
+
+    if update_types.iter().any(|t| *t != UpdateType::AlreadyInPath) {
+        write_list(&mut config_file, &list)?;
+    }
+
+    Ok(update_types)
+}
+
+/// Finds the line of the file listing the path
+pub fn locate(
+    name: &str,
+    path: &Path,
+    options: &UpdateOptions,
+) -> Result<Vec<Location>, PathmanError> {
+    let entry = resolve_path(path, options.find_home_dir()?.as_deref())?;
+
+    let config_file = open(name, options)?;
+    let list = parse_list(&config_file, options)?;
+
+    Ok(list
+        .position(&entry)
+        .map(|index| Location::ConfigFile {
+            path: config_file.path.clone(),
+            line: index + 1,
+        })
+        .into_iter()
+        .collect())
+}
+
+/// Removes the path from the file, deleting the file if no entry is left
+pub fn remove(
+    name: &str,
+    path: &Path,
+    options: &UpdateOptions,
+) -> Result<UpdateType, PathmanError> {
+    let entry = resolve_path(path, options.find_home_dir()?.as_deref())?;
+
+    let mut config_file = open(name, options)?;
+    let mut list = parse_list(&config_file, options)?;
+
+    if !list.remove(&entry) {
+        return Ok(UpdateType::NotInPath);
+    }
+
+    write_list(&mut config_file, &list)?;
+
+    Ok(UpdateType::Removed)
+}
+
+/// Replaces the old path with the new path in the file, adding the new path
+/// to the front if the old path is not present
+pub fn replace(
+    name: &str,
+    old: &Path,
+    new: &Path,
+    options: &UpdateOptions,
+) -> Result<UpdateType, PathmanError> {
+    let home = options.find_home_dir()?;
+    let old_entry = resolve_path(old, home.as_deref())?;
+    let new_entry = resolve_path(new, home.as_deref())?;
+
+    let mut config_file = open(name, options)?;
+    let mut list = parse_list(&config_file, options)?;
+
+    if !list.contains(&old_entry) {
+        let mut update_types = update(name, &[new], &Position::Front, options)?;
+
+        return Ok(update_types.remove(0));
+    }
+
+    if options.equivalence.equivalent(&old_entry, &new_entry) {
+        return Ok(UpdateType::AlreadyInPath);
+    }
+
+    list.replace(&old_entry, &new_entry);
+    write_list(&mut config_file, &list)?;
+
+    Ok(UpdateType::Replaced)
+}
@@ -1,110 +1,979 @@
 use crate::error::PathmanError;
 use crate::error::PathmanError::{
-    UnableToConvertPathToString, UnableToDetectShell, UnableToFindHomeDirectory,
-    UnableToFindShellConfigFile, UnsupportedShell,
+    UnableToConvertPathToString, UnableToFindHomeDirectory, UnableToFindShellConfigFile,
+    UnsupportedShell,
 };
-use home::home_dir;
+use crate::expand::{Expansion, Segment, parse};
+use crate::options::UpdateOptions;
+use crate::platform::Position;
+use crate::platform::unix::parser::split_statements;
 use std::env::var_os;
 use std::path::{Path, PathBuf};
 
+/// An entry added to the PATH by a shell configuration file
+pub struct Entry {
+    /// The entry, as written in the configuration file
+    pub value: String,
+
+    /// Whether the entry is added to the front or the back of the PATH
+    pub position: Position,
+}
+
+#[derive(Clone)]
 pub struct CurrentShell {
     pub shell: Shell,
-    pub home: PathBuf,
+
+    /// The home directory of the user, as seen from the target system, or
+    /// `None` for system-wide files, which are read by every user
+    pub home: Option<PathBuf>,
+
+    /// The root directory under which the files of the target system are
+    /// located
+    pub root: PathBuf,
 }
 
 /// The supported shells
+#[derive(Clone, PartialEq, Eq)]
 pub enum Shell {
     Bash,
     Zsh,
     Fish,
+
+    /// The syntax of systemd `environment.d` files, which only supports
+    /// assignments referencing other variables
+    Systemd,
 }
 
 impl CurrentShell {
-    /// Detects the current shell
-    pub fn detect() -> Result<Self, PathmanError> {
+    /// Detects the current shell, or uses the shell of the options
+    pub fn detect(options: &UpdateOptions) -> Result<Self, PathmanError> {
         // Retrieve the home directory
-        let home = match home_dir() {
-            Some(path) => path,
-            None => return Err(UnableToFindHomeDirectory),
-        };
+        let home = options.home_dir()?;
 
-        // Determine the shell from the SHELL environment variable
-        let shell = match var_os("SHELL") {
-            Some(shell) => shell.to_string_lossy().to_string(),
-            None => return Err(UnableToDetectShell),
-        };
+        // Determine the shell from the options or the SHELL environment
+        // variable
+        let shell = options.shell_name()?;
 
         // Match the shell string to supported shell kinds
-        match shell {
-            s if s.contains("bash") => Ok(CurrentShell {
-                shell: Shell::Bash,
-                home,
-            }),
-            s if s.contains("zsh") => Ok(CurrentShell {
-                shell: Shell::Zsh,
-                home,
-            }),
-            s if s.contains("fish") => Ok(CurrentShell {
-                shell: Shell::Fish,
-                home,
-            }),
-            _ => Err(UnsupportedShell(shell)),
+        let shell = match shell {
+            s if s.contains("bash") => Shell::Bash,
+            s if s.contains("zsh") => Shell::Zsh,
+            s if s.contains("fish") => Shell::Fish,
+            _ => return Err(UnsupportedShell(shell)),
+        };
+
+        Ok(CurrentShell {
+            shell,
+            home: Some(home),
+            root: options.root.clone(),
+        })
+    }
+
+    /// Creates the given shell, using the home directory of the options
+    pub fn new(shell: Shell, options: &UpdateOptions) -> Result<Self, PathmanError> {
+        Ok(CurrentShell {
+            shell,
+            home: Some(options.home_dir()?),
+            root: options.root.clone(),
+        })
+    }
+
+    /// Creates the given shell for system-wide files, which don't belong to
+    /// any home directory
+    pub fn system(shell: Shell, options: &UpdateOptions) -> Self {
+        CurrentShell {
+            shell,
+            home: None,
+            root: options.root.clone(),
         }
     }
 
+    /// Returns all the supported shells, using the home directory of the
+    /// options
+    pub fn all(options: &UpdateOptions) -> Result<Vec<Self>, PathmanError> {
+        [Shell::Bash, Shell::Zsh, Shell::Fish]
+            .into_iter()
+            .map(|shell| Self::new(shell, options))
+            .collect()
+    }
+
+    /// Returns the path of the given file of the target system, under the
+    /// root directory
+    fn staged(&self, path: &Path) -> PathBuf {
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+
     /// Returns the first shell configuration file that exists
     pub fn config_file_path(&self) -> Result<PathBuf, PathmanError> {
+        match self.config_file_paths().into_iter().next() {
+            Some(file) => Ok(file),
+            None => Err(UnableToFindShellConfigFile),
+        }
+    }
+
+    /// Returns the shell configuration file to create when none exists,
+    /// under the root directory
+    pub fn preferred_config_file_path(&self) -> Result<PathBuf, PathmanError> {
+        match self.candidate_config_file_paths().into_iter().next() {
+            Some(file) => Ok(file),
+            None => Err(UnableToFindShellConfigFile),
+        }
+    }
+
+    /// Returns the shell configuration files that exist under the root
+    /// directory, in order of preference
+    pub fn config_file_paths(&self) -> Vec<PathBuf> {
+        self.candidate_config_file_paths()
+            .into_iter()
+            .filter(|f| f.exists())
+            .collect()
+    }
+
+    /// Returns the shell configuration files, under the root directory, in
+    /// order of preference
+    fn candidate_config_file_paths(&self) -> Vec<PathBuf> {
+        let Some(home) = &self.home else {
+            return Vec::new();
+        };
+
         let files = match self.shell {
             Shell::Bash => vec![
-                self.home.join(".bashrc"),
-                self.home.join(".bash_profile"),
-                self.home.join(".profile"),
+                home.join(".bashrc"),
+                home.join(".bash_profile"),
+                home.join(".profile"),
             ],
-            Shell::Zsh => vec![self.home.join(".zshrc")],
-            Shell::Fish => vec![self.home.join(".config/fish/config.fish")],
+            Shell::Zsh => vec![home.join(".zshrc")],
+            Shell::Fish => vec![home.join(".config/fish/config.fish")],
+            Shell::Systemd => vec![],
         };
 
-        match files.into_iter().find(|f| f.exists()) {
-            Some(file) => Ok(file),
-            None => Err(UnableToFindShellConfigFile),
+        files.into_iter().map(|f| self.staged(&f)).collect()
+    }
+
+    /// Returns the path of the systemd `environment.d` file of the given name
+    ///
+    /// The file is located in `$XDG_CONFIG_HOME`, or in `~/.config` if the
+    /// variable is unset or not an absolute path. The variable is ignored
+    /// when the files are located under another root directory, as it
+    /// belongs to the current system.
+    pub fn environment_d_path(&self, name: &str) -> Result<PathBuf, PathmanError> {
+        let config_home = var_os("XDG_CONFIG_HOME")
+            .filter(|_| self.root == Path::new("/"))
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| self.home.as_ref().map(|home| home.join(".config")));
+        let Some(config_home) = config_home else {
+            return Err(UnableToFindHomeDirectory);
+        };
+
+        Ok(self.staged(
+            &config_home
+                .join("environment.d")
+                .join(format!("{name}.conf")),
+        ))
+    }
+
+    /// Returns the path of the system-wide configuration file of the given
+    /// name, under the root directory
+    pub fn system_config_path(&self, name: &str) -> PathBuf {
+        let path = match self.shell {
+            Shell::Bash | Shell::Zsh => format!("/etc/profile.d/{name}.sh"),
+            Shell::Fish => format!("/usr/share/fish/vendor_conf.d/{name}.fish"),
+            Shell::Systemd => format!("/etc/environment.d/{name}.conf"),
+        };
+
+        self.staged(Path::new(&path))
+    }
+
+    /// Formats the path for use in a double-quoted shell word
+    ///
+    /// With deferred expansion, `~` and variable references in the path are
+    /// written in the syntax of the shell. Otherwise, the path is taken
+    /// literally and, when it is located in the home directory and the
+    /// `home_relative` option is set, the home directory is replaced with
+    /// `$HOME`. In system-wide files, a leading `~` is always written as
+    /// `$HOME`, as the home directory depends on the user.
+    pub fn format_path<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &UpdateOptions,
+    ) -> Result<String, PathmanError> {
+        let path = path.as_ref();
+
+        if options.expansion == Expansion::Deferred {
+            let path = match path.to_str() {
+                Some(p) => p,
+                None => return Err(UnableToConvertPathToString),
+            };
+
+            return Ok(self.format_segments(parse(path)));
+        }
+
+        let relative = match &self.home {
+            Some(home) if options.home_relative => path.strip_prefix(home).ok(),
+            Some(_) => None,
+            None => path.strip_prefix("~").ok(),
+        };
+        if let Some(relative) = relative {
+            let relative = match relative.to_str() {
+                Some(r) => r,
+                None => return Err(UnableToConvertPathToString),
+            };
+
+            return match relative {
+                "" => Ok("$HOME".to_string()),
+                r => Ok(format!("$HOME/{}", self.escape(r))),
+            };
         }
+
+        match path.to_str() {
+            Some(p) => Ok(self.escape(p)),
+            None => Err(UnableToConvertPathToString),
+        }
+    }
+
+    /// Formats the value of a variable, either literally or like a path
+    /// depending on the options
+    pub fn format_value(
+        &self,
+        value: &str,
+        options: &UpdateOptions,
+    ) -> Result<String, PathmanError> {
+        match options.literal {
+            true => Ok(self.escape(value)),
+            false => self.format_path(value, options),
+        }
+    }
+
+    /// Writes the segments of a path in the syntax of the shell, so that `~`
+    /// and variable references are expanded by the shell.
+    fn format_segments(&self, segments: Vec<Segment>) -> String {
+        let mut formatted = String::new();
+        let mut after_variable = false;
+
+        for segment in segments {
+            match segment {
+                Segment::Literal(literal) => {
+                    // Fish has no braced variable syntax inside quotes, so an
+                    // empty string is used to terminate the variable name
+                    let starts_with_name =
+                        literal.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_');
+                    if after_variable && starts_with_name && self.shell != Shell::Systemd {
+                        formatted.push_str("\"\"");
+                    }
+                    formatted.push_str(&self.escape(&literal));
+                    after_variable = false;
+                }
+                Segment::Home => {
+                    formatted.push_str("$HOME");
+                    after_variable = false;
+                }
+                Segment::Variable(name) => {
+                    match self.shell {
+                        Shell::Bash | Shell::Zsh | Shell::Systemd => {
+                            formatted.push_str(&format!("${{{name}}}"))
+                        }
+                        Shell::Fish => formatted.push_str(&format!("${name}")),
+                    }
+                    after_variable = true;
+                }
+            }
+        }
+
+        formatted
+    }
+
+    /// Returns the name of the shell
+    pub fn name(&self) -> &'static str {
+        match self.shell {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::Systemd => "systemd",
+        }
+    }
+
+    /// Builds the command reloading the given configuration file in the
+    /// current shell session
+    pub fn get_source_command(&self, config_file: &Path) -> String {
+        format!("source {}", self.format_file(config_file))
+    }
+
+    /// Builds the command loading the variables assigned by the given file,
+    /// written in the syntax of pam_env or systemd, in the current shell
+    /// session, if the shell can read it
+    pub fn get_import_command(&self, file: &Path) -> Option<String> {
+        match self.shell {
+            // The variables are exported as they are assigned
+            Shell::Bash | Shell::Zsh => {
+                Some(format!("set -a; . {}; set +a", self.format_file(file)))
+            }
+            Shell::Fish | Shell::Systemd => None,
+        }
+    }
+
+    /// Builds the command rebuilding the PATH from the `paths.d` directories
+    /// in the current shell session, as done by macOS for login shells
+    pub fn get_path_helper_command(&self) -> Option<String> {
+        match self.shell {
+            Shell::Bash | Shell::Zsh => Some("eval \"$(/usr/libexec/path_helper -s)\"".to_string()),
+            Shell::Fish => Some("eval (/usr/libexec/path_helper -c)".to_string()),
+            Shell::Systemd => None,
+        }
+    }
+
+    /// Formats the path of the given file for use as a shell word, relative
+    /// to the home directory when possible
+    fn format_file(&self, config_file: &Path) -> String {
+        let is_plain = |path: &str| {
+            path.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '.' | '_' | '-'))
+        };
+
+        let relative = self
+            .home
+            .as_ref()
+            .and_then(|home| config_file.strip_prefix(home).ok());
+        match relative {
+            Some(relative) if is_plain(&relative.to_string_lossy()) => {
+                format!("~/{}", relative.to_string_lossy())
+            }
+            Some(relative) => format!("\"$HOME/{}\"", self.escape(&relative.to_string_lossy())),
+            None => {
+                let file = config_file.to_string_lossy();
+                match is_plain(&file) {
+                    true => file.to_string(),
+                    false => format!("\"{}\"", self.escape(&file)),
+                }
+            }
+        }
+    }
+
+    /// Escapes the characters that are special inside double quotes, or in
+    /// unquoted values for systemd
+    fn escape(&self, literal: &str) -> String {
+        let special: &[char] = match self.shell {
+            Shell::Bash | Shell::Zsh => &['\\', '"', '$', '`'],
+            Shell::Fish => &['\\', '"', '$'],
+            Shell::Systemd => &['\\', '"', '\'', '$', ' '],
+        };
+
+        let mut escaped = String::with_capacity(literal.len());
+        for c in literal.chars() {
+            if special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+
+        escaped
+    }
+
+    /// Formats the given paths as a list of PATH entries
+    ///
+    /// The entries are joined with colons for bash and zsh, to be wrapped in
+    /// double quotes, and are quoted as separate words for fish.
+    fn format_path_list<P: AsRef<Path>>(
+        &self,
+        paths: &[P],
+        options: &UpdateOptions,
+    ) -> Result<String, PathmanError> {
+        let paths = paths
+            .iter()
+            .map(|path| self.format_path(path, options))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let list = match self.shell {
+            Shell::Bash | Shell::Zsh | Shell::Systemd => paths.join(":"),
+            Shell::Fish => paths
+                .iter()
+                .map(|path| format!("\"{path}\""))
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+
+        Ok(list)
+    }
+
+    /// Returns the beginning of the fish command setting the variable
+    fn fish_set(variable: &str) -> String {
+        // Fish only exports variables whose name ends in PATH as
+        // colon-separated lists by default
+        match variable.ends_with("PATH") {
+            true => format!("set -gx {variable}"),
+            false => format!("set -gx --path {variable}"),
+        }
+    }
+
+    /// Returns the value that stands for the given variable when it is unset
+    /// or empty, as defined by the XDG Base Directory Specification
+    fn default_value(variable: &str) -> Option<&'static str> {
+        match variable {
+            "XDG_DATA_DIRS" => Some("/usr/local/share:/usr/share"),
+            "XDG_CONFIG_DIRS" => Some("/etc/xdg"),
+            _ => None,
+        }
+    }
+
+    /// Builds the shell command for prepending to the variable of the options
+    pub fn get_prepend_command<P: AsRef<Path>>(
+        &self,
+        paths: &[P],
+        options: &UpdateOptions,
+    ) -> Result<String, PathmanError> {
+        let paths = self.format_path_list(paths, options)?;
+        let variable = &options.variable;
+
+        let command = match (&self.shell, variable.as_str()) {
+            // The PATH is always set, and an empty entry in the MANPATH stands
+            // for the default search path, so the separator is always written
+            (Shell::Bash | Shell::Zsh, "PATH" | "MANPATH") => {
+                format!("export {variable}=\"{paths}:${variable}\"")
+            }
+            // The default value of an unset variable must be written out, as
+            // it no longer applies once the variable is set
+            (Shell::Bash | Shell::Zsh, _) if let Some(default) = Self::default_value(variable) => {
+                format!("export {variable}=\"{paths}:${{{variable}:-{default}}}\"")
+            }
+            // Other variables may be unset, in which case an empty entry would
+            // refer to the working directory
+            (Shell::Bash | Shell::Zsh, _) => {
+                format!("export {variable}=\"{paths}${{{variable}:+:${variable}}}\"")
+            }
+            (Shell::Fish, _) => {
+                format!("{} {paths} ${variable}", Self::fish_set(variable))
+            }
+            (Shell::Systemd, "PATH" | "MANPATH") => {
+                format!("{variable}={paths}:${{{variable}}}")
+            }
+            (Shell::Systemd, _) if let Some(default) = Self::default_value(variable) => {
+                format!("{variable}={paths}:${{{variable}:-{default}}}")
+            }
+            (Shell::Systemd, _) => {
+                format!("{variable}={paths}${{{variable}:+:${variable}}}")
+            }
+        };
+
+        Ok(command)
     }
 
-    /// Builds the shell command for prepending to the PATH environment variable
-    pub fn get_prepend_command<P: AsRef<Path>>(&self, path: P) -> Result<String, PathmanError> {
-        let path = match path.as_ref().to_str() {
-            Some(p) => p,
-            None => return Err(UnableToConvertPathToString),
+    /// Builds the shell command for appending to the variable of the options
+    pub fn get_append_command<P: AsRef<Path>>(
+        &self,
+        paths: &[P],
+        options: &UpdateOptions,
+    ) -> Result<String, PathmanError> {
+        let paths = self.format_path_list(paths, options)?;
+        let variable = &options.variable;
+
+        let command = match (&self.shell, variable.as_str()) {
+            (Shell::Bash | Shell::Zsh, "PATH" | "MANPATH") => {
+                format!("export {variable}=\"${variable}:{paths}\"")
+            }
+            (Shell::Bash | Shell::Zsh, _) if let Some(default) = Self::default_value(variable) => {
+                format!("export {variable}=\"${{{variable}:-{default}}}:{paths}\"")
+            }
+            (Shell::Bash | Shell::Zsh, _) => {
+                format!("export {variable}=\"${{{variable}:+${variable}:}}{paths}\"")
+            }
+            (Shell::Fish, _) => {
+                format!("{} ${variable} {paths}", Self::fish_set(variable))
+            }
+            (Shell::Systemd, "PATH" | "MANPATH") => {
+                format!("{variable}=${{{variable}}}:{paths}")
+            }
+            (Shell::Systemd, _) if let Some(default) = Self::default_value(variable) => {
+                format!("{variable}=${{{variable}:-{default}}}:{paths}")
+            }
+            (Shell::Systemd, _) => {
+                format!("{variable}=${{{variable}:+${variable}:}}{paths}")
+            }
         };
 
+        Ok(command)
+    }
+
+    /// Builds the command initializing the variable of the options before it
+    /// is updated, if needed
+    ///
+    /// When the MANPATH is unset, fish expands it to nothing, which would drop
+    /// the empty entry standing for the default search path, so it is set to
+    /// a single empty entry first. Likewise, the XDG variables are set to
+    /// their default value when they are unset or empty.
+    pub fn get_init_command(&self, options: &UpdateOptions) -> Option<String> {
+        let variable = options.variable.as_str();
+
+        match (&self.shell, variable) {
+            (Shell::Fish, "MANPATH") => Some("set -q MANPATH; or set -gx MANPATH \"\"".to_string()),
+            (Shell::Fish, _) => Self::default_value(variable).map(|default| {
+                let defaults: Vec<String> =
+                    default.split(':').map(|d| format!("\"{d}\"")).collect();
+
+                format!(
+                    "set -q {variable}[1]; or {} {}",
+                    Self::fish_set(variable),
+                    defaults.join(" ")
+                )
+            }),
+            _ => None,
+        }
+    }
+
+    /// Builds the shell command for adding to the PATH environment variable at
+    /// the given position
+    pub fn get_command<P: AsRef<Path>>(
+        &self,
+        paths: &[P],
+        position: &Position,
+        options: &UpdateOptions,
+    ) -> Result<String, PathmanError> {
+        match position {
+            Position::Front => self.get_prepend_command(paths, options),
+            Position::Back => self.get_append_command(paths, options),
+            Position::Before { anchor, .. } | Position::After { anchor, .. } => {
+                let before = matches!(position, Position::Before { .. });
+                let fallback = match position.fallback() {
+                    Position::Front => self.get_prepend_command(paths, options)?,
+                    _ => self.get_append_command(paths, options)?,
+                };
+
+                self.get_splice_command(paths, anchor, before, &fallback, options)
+            }
+        }
+    }
+
+    /// Builds the shell command for inserting into the PATH environment
+    /// variable before or after the anchor entry
+    ///
+    /// The PATH is spliced when the shell starts, and the fallback command is
+    /// run instead if the anchor is not in the PATH at that time. Systemd
+    /// cannot look for the anchor, so the fallback command is always used.
+    pub fn get_splice_command<P: AsRef<Path>, A: AsRef<Path>>(
+        &self,
+        paths: &[P],
+        anchor: A,
+        before: bool,
+        fallback: &str,
+        options: &UpdateOptions,
+    ) -> Result<String, PathmanError> {
+        let paths = self.format_path_list(paths, options)?;
+        let anchor = self.format_path(anchor, options)?;
+        let v = &options.variable;
+
         let command = match self.shell {
             Shell::Bash | Shell::Zsh => {
-                format!("export PATH=\"{path}:$PATH\"")
+                // The PATH is wrapped in colons so that the anchor can be
+                // matched as a whole entry, wherever it is. The anchor is
+                // quoted in the patterns, so that `*`, `?` and `[` in it are
+                // matched literally
+                let spliced = match before {
+                    true => format!(
+                        "${{{v}%%:\"{anchor}\":*}}:{paths}:{anchor}:${{{v}#*:\"{anchor}\":}}"
+                    ),
+                    false => format!(
+                        "${{{v}%%:\"{anchor}\":*}}:{anchor}:{paths}:${{{v}#*:\"{anchor}\":}}"
+                    ),
+                };
+
+                format!(
+                    "case \":${v}:\" in *:\"{anchor}\":*) {v}=\":${v}:\"; {v}=\"{spliced}\"; {v}=\"${{{v}#:}}\"; export {v}=\"${{{v}%:}}\" ;; *) {fallback} ;; esac"
+                )
             }
             Shell::Fish => {
-                format!("set -gx PATH \"{path}\" $PATH")
+                // The PATH is rebuilt entry by entry, inserting the paths next
+                // to the anchor
+                let rebuild = match before {
+                    true => format!(
+                        "test \"$entry\" = \"{anchor}\"; and set -a spliced {paths}; set -a spliced $entry"
+                    ),
+                    false => format!(
+                        "set -a spliced $entry; test \"$entry\" = \"{anchor}\"; and set -a spliced {paths}"
+                    ),
+                };
+
+                format!(
+                    "if contains -- \"{anchor}\" ${v}; set -l spliced; for entry in ${v}; {rebuild}; end; {} $spliced; else; {fallback}; end",
+                    Self::fish_set(v)
+                )
             }
+            Shell::Systemd => fallback.to_string(),
         };
 
         Ok(command)
     }
 
-    /// Builds the shell command for appending to the PATH environment variable
-    pub fn get_append_command<P: AsRef<Path>>(&self, path: P) -> Result<String, PathmanError> {
-        let path = match path.as_ref().to_str() {
-            Some(p) => p,
-            None => return Err(UnableToConvertPathToString),
+    /// Returns the anchor of the splice command written by
+    /// [`CurrentShell::get_splice_command`] in the given line, as written in
+    /// the line, if any
+    pub fn splice_anchor<'a>(&self, line: &'a str) -> Option<&'a str> {
+        let (start, end) = match self.shell {
+            Shell::Bash | Shell::Zsh => (" in *:\"", "\":*)"),
+            Shell::Fish => ("if contains -- \"", "\" $"),
+            Shell::Systemd => return None,
+        };
+
+        Some(line.split_once(start)?.1.split_once(end)?.0)
+    }
+
+    /// Builds the shell command setting the given variable to the given value
+    pub fn get_set_command(
+        &self,
+        name: &str,
+        value: &str,
+        options: &UpdateOptions,
+    ) -> Result<String, PathmanError> {
+        let value = self.format_value(value, options)?;
+
+        let command = match self.shell {
+            Shell::Bash | Shell::Zsh => {
+                format!("export {name}=\"{value}\"")
+            }
+            Shell::Fish => {
+                format!("set -gx {name} \"{value}\"")
+            }
+            Shell::Systemd => {
+                format!("{name}={value}")
+            }
         };
 
+        Ok(command)
+    }
+
+    /// Wraps the given command in a check for the existence of the directory
+    ///
+    /// The resulting line only runs the command when the directory exists at
+    /// shell startup.
+    pub fn get_guarded_command<P: AsRef<Path>>(
+        &self,
+        path: P,
+        command: &str,
+        options: &UpdateOptions,
+    ) -> Result<String, PathmanError> {
+        let path = self.format_path(path, options)?;
+
         let command = match self.shell {
             Shell::Bash | Shell::Zsh => {
-                format!("export PATH=\"$PATH:{path}\"")
+                format!("[ -d \"{path}\" ] && {command}")
             }
             Shell::Fish => {
-                format!("set -gx PATH $PATH \"{path}\"")
+                format!("test -d \"{path}\"; and {command}")
             }
+            // Systemd has no conditionals, so the entry is always added
+            Shell::Systemd => command.to_string(),
         };
 
         Ok(command)
     }
+
+    /// Finds the byte ranges of the given entry, as written in the line of
+    /// the shell configuration file
+    ///
+    /// Only whole entries are found, so that `/opt/tool` does not match
+    /// `/opt/tool/bin`.
+    fn find_entry(&self, line: &str, entry: &str) -> Vec<(usize, usize)> {
+        let is_boundary = |c: Option<char>| {
+            c.is_none_or(|c| c.is_whitespace() || matches!(c, '"' | '\'' | ':' | ';'))
+        };
+        // Conditional references to the variable directly follow or precede
+        // the entries (e.g. `/x${VAR:+:$VAR}` or `${VAR:+$VAR:}/x`), and
+        // unquoted values directly follow the assignment
+        let is_boundary_before = |c: Option<char>| is_boundary(c) || matches!(c, Some('}' | '='));
+        let is_boundary_after = |c: Option<char>| is_boundary(c) || c == Some('$');
+
+        // The entry was unescaped when parsed, so it may be written escaped
+        for written in [entry.to_string(), self.escape(entry)] {
+            let ranges: Vec<(usize, usize)> = line
+                .match_indices(&written)
+                .map(|(start, _)| (start, start + written.len()))
+                .filter(|(start, end)| {
+                    is_boundary_before(line[..*start].chars().next_back())
+                        && is_boundary_after(line[*end..].chars().next())
+                })
+                .collect();
+
+            if !ranges.is_empty() {
+                return ranges;
+            }
+        }
+
+        Vec::new()
+    }
+
+    /// Replaces the given entry, as written in the line of the shell
+    /// configuration file, with the given replacement
+    ///
+    /// Only whole entries are replaced, so that replacing `/opt/tool` does not
+    /// affect `/opt/tool/bin`.
+    pub fn replace_entry(&self, line: &str, entry: &str, replacement: &str) -> String {
+        let mut replaced = line.to_string();
+
+        for (start, end) in self.find_entry(line, entry).into_iter().rev() {
+            replaced.replace_range(start..end, replacement);
+        }
+
+        replaced
+    }
+
+    /// Removes the given entry, as written in the line of the shell
+    /// configuration file, along with its separator
+    pub fn remove_entry(&self, line: &str, entry: &str) -> String {
+        let separator = match self.shell {
+            Shell::Bash | Shell::Zsh | Shell::Systemd => ':',
+            Shell::Fish => ' ',
+        };
+
+        let mut removed = line.to_string();
+        // The ranges are removed from the end, so a range must not extend past
+        // the start of the previously removed one
+        let mut limit = line.len();
+
+        for (mut start, mut end) in self.find_entry(line, entry).into_iter().rev() {
+            // Fish entries are written as separate quoted words
+            if self.shell == Shell::Fish
+                && line[..start].ends_with('"')
+                && line[end..].starts_with('"')
+            {
+                start -= 1;
+                end += 1;
+            }
+
+            if line[end..].starts_with(separator) {
+                end += 1;
+            } else if line[..start].ends_with(separator) {
+                start -= 1;
+            }
+
+            removed.replace_range(start..end.min(limit), "");
+            limit = start;
+        }
+
+        removed
+    }
+
+    /// Extracts the value assigned to the given variable by the given line of
+    /// the shell configuration file, if any
+    ///
+    /// The value is returned as written, without expanding variables.
+    pub fn parse_assignment(&self, line: &str, name: &str) -> Option<String> {
+        let mut value = None;
+
+        for statement in split_statements(line) {
+            let words: Vec<&str> = statement.iter().map(String::as_str).collect();
+
+            match self.shell {
+                Shell::Bash | Shell::Zsh | Shell::Systemd => {
+                    let assignments = match words.as_slice() {
+                        ["export", rest @ ..] => rest,
+                        rest => rest,
+                    };
+
+                    for assignment in assignments.iter().take_while(|w| w.contains('=')) {
+                        if let Some(v) = assignment
+                            .strip_prefix(name)
+                            .and_then(|a| a.strip_prefix('='))
+                        {
+                            value = Some(v.to_string());
+                        }
+                    }
+                }
+                Shell::Fish => {
+                    let words = match words.as_slice() {
+                        ["and" | "or", rest @ ..] => rest,
+                        rest => rest,
+                    };
+
+                    let ["set", rest @ ..] = words else {
+                        continue;
+                    };
+
+                    let flags: Vec<&str> = rest
+                        .iter()
+                        .take_while(|w| w.starts_with('-'))
+                        .copied()
+                        .collect();
+                    let is_query_or_erase = flags.iter().any(|f| {
+                        matches!(*f, "--erase" | "--query")
+                            || (!f.starts_with("--") && f.contains(['e', 'q']))
+                    });
+
+                    if let [n, values @ ..] = &rest[flags.len()..]
+                        && *n == name
+                        && !is_query_or_erase
+                    {
+                        value = Some(values.join(" "));
+                    }
+                }
+            }
+        }
+
+        value
+    }
+
+    /// Extracts the entries added to the given variable by the given line of
+    /// the shell configuration file
+    ///
+    /// The entries are returned as written, without expanding variables, and
+    /// references to the variable itself are left out.
+    pub fn parse_entries(&self, line: &str, variable: &str) -> Vec<Entry> {
+        let reference = format!("${variable}");
+        let braced_reference = format!("${{{variable}}}");
+        let mut entries = Vec::new();
+
+        // Whether the statement is part of a splice command, which is skipped
+        // up to its fallback command
+        let mut in_splice = false;
+
+        for statement in split_statements(line) {
+            let words: Vec<&str> = statement.iter().map(String::as_str).collect();
+
+            match self.shell {
+                Shell::Bash | Shell::Zsh | Shell::Systemd => {
+                    let words = match (in_splice, words.as_slice()) {
+                        (_, ["case", ..]) => {
+                            in_splice = true;
+                            continue;
+                        }
+                        (true, ["*)", rest @ ..]) => {
+                            in_splice = false;
+                            rest
+                        }
+                        (true, _) => continue,
+                        (false, words) => words,
+                    };
+
+                    let assignments = match words {
+                        ["export", rest @ ..] => rest,
+                        rest => rest,
+                    };
+
+                    for assignment in assignments.iter().take_while(|w| w.contains('=')) {
+                        if let Some(value) = assignment
+                            .strip_prefix(variable)
+                            .and_then(|a| a.strip_prefix('='))
+                        {
+                            // References that only add a separator when the
+                            // variable is set, or that fall back to its default
+                            // value, are handled as plain references
+                            let mut value = value.to_string();
+                            if let Some(default) = Self::default_value(variable) {
+                                value = value
+                                    .replace(&format!("${{{variable}:-{default}}}"), &reference);
+                            }
+                            let value = value
+                                .replace(
+                                    &format!("${{{variable}:+:{reference}}}"),
+                                    &format!(":{reference}"),
+                                )
+                                .replace(
+                                    &format!("${{{variable}:+{reference}:}}"),
+                                    &format!("{reference}:"),
+                                );
+
+                            let values: Vec<&str> = value.split(':').collect();
+                            entries.extend(Self::split_values(
+                                &values,
+                                &[&reference, &braced_reference],
+                            ));
+                        }
+                    }
+                }
+                Shell::Fish => {
+                    // The default value set by the initialization command
+                    // isn't an entry
+                    let is_init = words.first() == Some(&"or");
+
+                    // Skip the combiners used by guarded commands
+                    let words = match words.as_slice() {
+                        ["and" | "or", rest @ ..] => rest,
+                        rest => rest,
+                    };
+
+                    match (in_splice, words) {
+                        (_, ["if", "contains", ..]) => {
+                            in_splice = true;
+                            continue;
+                        }
+                        (true, ["else"]) => {
+                            in_splice = false;
+                            continue;
+                        }
+                        (true, _) => continue,
+                        (false, _) => {}
+                    }
+
+                    let (command, rest) = match words {
+                        [command @ ("set" | "fish_add_path"), rest @ ..] => (*command, rest),
+                        _ => continue,
+                    };
+
+                    let flags: Vec<&str> = rest
+                        .iter()
+                        .take_while(|w| w.starts_with('-'))
+                        .copied()
+                        .collect();
+                    let has_flag = |short: char, long: &str| {
+                        flags.iter().any(|f| match f.strip_prefix("--") {
+                            Some(name) => name == long,
+                            None => f.contains(short),
+                        })
+                    };
+
+                    let values = match (command, &rest[flags.len()..]) {
+                        ("set", _) if has_flag('e', "erase") || has_flag('q', "query") => {
+                            continue;
+                        }
+                        ("set", [name, values @ ..]) if *name == variable => values,
+                        ("fish_add_path", values) if variable == "PATH" => values,
+                        _ => continue,
+                    };
+
+                    // Empty values are only used to initialize the MANPATH
+                    let values: Vec<&str> =
+                        values.iter().filter(|v| !v.is_empty()).copied().collect();
+                    if is_init
+                        && Self::default_value(variable)
+                            .is_some_and(|default| default.split(':').eq(values.iter().copied()))
+                    {
+                        continue;
+                    }
+
+                    let position = match (has_flag('a', "append"), has_flag('p', "prepend")) {
+                        (true, _) => Some(Position::Back),
+                        (_, true) => Some(Position::Front),
+                        _ if command == "fish_add_path" => Some(Position::Front),
+                        _ => None,
+                    };
+
+                    match position {
+                        Some(position) => entries.extend(values.iter().map(|value| Entry {
+                            value: value.to_string(),
+                            position: position.clone(),
+                        })),
+                        None => entries.extend(Self::split_values(&values, &[&reference])),
+                    }
+                }
+            }
+        }
+
+        entries
+    }
+
+    /// Determines the position of each value in an assignment, based on
+    /// whether it comes before or after the reference to the variable itself.
+    fn split_values(values: &[&str], references: &[&str]) -> Vec<Entry> {
+        let reference = values.iter().position(|v| references.contains(v));
+
+        values
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != reference)
+            .map(|(index, value)| Entry {
+                value: value.to_string(),
+                position: match reference {
+                    Some(reference) if index > reference => Position::Back,
+                    _ => Position::Front,
+                },
+            })
+            .collect()
+    }
 }
@@ -1,99 +1,540 @@
-use crate::PathmanError::{UnableToOpenEnvironmentKey, UnableToWritePathEnvironmentKey};
+use crate::PathmanError::{
+    NoBackup, UnableToConvertPathToString, UnableToFindHomeDirectory, UnableToOpenEnvironmentKey,
+    UnableToReadShellConfigFile, UnableToWritePathEnvironmentKey, UnableToWriteShellConfigFile,
+    UnsupportedTarget,
+};
 use crate::UpdateType;
+use crate::activation::Activation;
+use crate::backup::Backup;
 use crate::error::PathmanError;
-use crate::platform::PathUpdater;
-use std::path::Path;
-use winreg::RegKey;
-use winreg::enums::{HKEY_CURRENT_USER, KEY_SET_VALUE};
+use crate::expand::{Expansion, Segment, expand, parse};
+use crate::options::UpdateOptions;
+use crate::path_list::PathList;
+use crate::platform::{PathUpdater, Position};
+use crate::status::Location;
+use crate::target::Target;
+use home::home_dir;
+use std::path::{Path, PathBuf};
+use winreg::enums::RegType::{self, *};
+use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, KEY_SET_VALUE};
+use winreg::types::FromRegValue;
+use winreg::{RegKey, RegValue};
+
+/// The types of registry values, which the backups are named after
+const VALUE_TYPES: [RegType; 12] = [
+    REG_NONE,
+    REG_SZ,
+    REG_EXPAND_SZ,
+    REG_BINARY,
+    REG_DWORD,
+    REG_DWORD_BIG_ENDIAN,
+    REG_LINK,
+    REG_MULTI_SZ,
+    REG_RESOURCE_LIST,
+    REG_FULL_RESOURCE_DESCRIPTOR,
+    REG_RESOURCE_REQUIREMENTS_LIST,
+    REG_QWORD,
+];
 
 /// Path Updater for Windows
 pub struct WindowsPathUpdater;
 
 impl WindowsPathUpdater {
-    /// Retrieves the user's current PATH environment variable from the Windows registry.
-    fn get_path() -> Result<String, PathmanError> {
+    /// Returns the name of the registry value storing the variable of the
+    /// options.
+    fn value_name(options: &UpdateOptions) -> &str {
+        match options.variable.as_str() {
+            "PATH" => "Path",
+            variable => variable,
+        }
+    }
+
+    /// Checks that the target of the options is the registry, as the other
+    /// targets are specific to macOS and Linux.
+    fn check_target(options: &UpdateOptions) -> Result<(), PathmanError> {
+        match options.target {
+            Target::ShellConfig => Ok(()),
+            _ => Err(UnsupportedTarget),
+        }
+    }
+
+    /// Retrieves the user's current value of the variable of the options from
+    /// the Windows registry.
+    fn get_path(options: &UpdateOptions) -> Result<String, PathmanError> {
+        Self::check_target(options)?;
+
+        Ok(Self::get_value(Self::value_name(options), options)?.unwrap_or_default())
+    }
+
+    /// Retrieves the user's value of the given variable from the Windows
+    /// registry, if it is set.
+    fn get_value(name: &str, options: &UpdateOptions) -> Result<Option<String>, PathmanError> {
+        Ok(Self::get_raw_value(name, options)?
+            .and_then(|value| String::from_reg_value(&value).ok()))
+    }
+
+    /// Retrieves the user's value of the given variable from the Windows
+    /// registry along with its type, if it is set.
+    fn get_raw_value(
+        name: &str,
+        options: &UpdateOptions,
+    ) -> Result<Option<RegValue<'static>>, PathmanError> {
         let hkcu = RegKey::predef(HKEY_CURRENT_USER);
 
-        let env_key = match hkcu.open_subkey("Environment") {
+        let env_key = match hkcu.open_subkey(&options.registry_key) {
             Ok(env_key) => env_key,
             Err(_) => return Err(UnableToOpenEnvironmentKey),
         };
 
-        let path = env_key.get_value("Path").unwrap_or_else(|_| String::new());
+        Ok(env_key.get_raw_value(name).ok())
+    }
+
+    /// Retrieves the system-wide value of the variable of the options from
+    /// the Windows registry.
+    ///
+    /// An empty value is returned if the registry key cannot be read.
+    fn get_system_path(options: &UpdateOptions) -> String {
+        let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+
+        hklm.open_subkey(r"SYSTEM\CurrentControlSet\Control\Session Manager\Environment")
+            .and_then(|env_key| env_key.get_value(Self::value_name(options)))
+            .unwrap_or_default()
+    }
+
+    /// Set the value of the variable of the options in the registry
+    fn set_path(new_path: &str, options: &UpdateOptions) -> Result<(), PathmanError> {
+        Self::check_target(options)?;
+
+        Self::back_up(Self::value_name(options), options)?;
+        Self::set_value(Self::value_name(options), new_path, false, options)
+    }
 
-        Ok(path)
+    /// Returns the file standing for the given variable in the backups
+    fn backup_file(name: &str, options: &UpdateOptions) -> PathBuf {
+        Path::new("registry").join(&options.registry_key).join(name)
     }
 
-    /// Set the PATH value in the registry
-    fn set_path(new_path: &str) -> Result<(), PathmanError> {
+    /// Backs up the user's value of the given variable, or records that it
+    /// isn't set, if backups are enabled, and deletes the oldest backups
+    fn back_up(name: &str, options: &UpdateOptions) -> Result<(), PathmanError> {
+        let Some(backup) = options.backup_settings()? else {
+            return Ok(());
+        };
+
+        // The type of the value is recorded in the name of the backup, so
+        // that restoring it doesn't depend on its content
+        let file = Self::backup_file(name, options);
+        let (path, bytes) = match Self::get_raw_value(name, options)? {
+            Some(value) => {
+                let mut path = backup.create_path(&file).into_os_string();
+                path.push(format!(".{:?}", value.vtype));
+
+                (PathBuf::from(path), value.bytes.into_owned())
+            }
+            None => (backup.create_absent_path(&file), Vec::new()),
+        };
+
+        let result = match path.parent() {
+            Some(parent) => std::fs::create_dir_all(parent),
+            None => Ok(()),
+        }
+        .and_then(|_| std::fs::write(&path, bytes));
+
+        if result.is_err() {
+            return Err(UnableToWriteShellConfigFile(
+                path.to_string_lossy().to_string(),
+            ));
+        }
+
+        backup.prune(&file);
+        Ok(())
+    }
+
+    /// Set the user's value of the given variable in the registry
+    ///
+    /// Literal values are never expanded by Windows, even if they contain
+    /// `%` signs.
+    fn set_value(
+        name: &str,
+        value: &str,
+        literal: bool,
+        options: &UpdateOptions,
+    ) -> Result<(), PathmanError> {
+        // Values referencing environment variables must be stored as
+        // REG_EXPAND_SZ for Windows to expand them
+        let vtype = match value.contains('%') && !literal {
+            true => REG_EXPAND_SZ,
+            false => REG_SZ,
+        };
+        let bytes = value
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<u8>>();
+
+        Self::set_raw_value(
+            name,
+            &RegValue {
+                bytes: bytes.into(),
+                vtype,
+            },
+            options,
+        )
+    }
+
+    /// Set the user's value of the given variable in the registry, along
+    /// with its type
+    fn set_raw_value(
+        name: &str,
+        value: &RegValue,
+        options: &UpdateOptions,
+    ) -> Result<(), PathmanError> {
         let hkcu = RegKey::predef(HKEY_CURRENT_USER);
 
-        let env_key = match hkcu.open_subkey_with_flags("Environment", KEY_SET_VALUE) {
+        let env_key = match hkcu.open_subkey_with_flags(&options.registry_key, KEY_SET_VALUE) {
             Ok(env_key) => env_key,
             Err(_) => return Err(UnableToOpenEnvironmentKey),
         };
 
-        match env_key.set_value("Path", &new_path) {
-            Ok(_) => {}
-            Err(_) => return Err(UnableToWritePathEnvironmentKey),
+        match env_key.set_raw_value(name, value) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(UnableToWritePathEnvironmentKey),
         }
+    }
 
-        Ok(())
+    /// Delete the user's value of the given variable from the registry
+    fn delete_value(name: &str, options: &UpdateOptions) -> Result<(), PathmanError> {
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+
+        let env_key = match hkcu.open_subkey_with_flags(&options.registry_key, KEY_SET_VALUE) {
+            Ok(env_key) => env_key,
+            Err(_) => return Err(UnableToOpenEnvironmentKey),
+        };
+
+        match env_key.delete_value(name) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(UnableToWritePathEnvironmentKey),
+        }
+    }
+
+    /// Resolves `~` and environment variables in the given path.
+    ///
+    /// They are either expanded immediately, or rewritten as `%VAR%`
+    /// references that Windows expands whenever the PATH is read.
+    fn resolve_path(path: &Path, options: &UpdateOptions) -> Result<String, PathmanError> {
+        let path = match path.to_str() {
+            Some(p) => p,
+            None => return Err(UnableToConvertPathToString),
+        };
+
+        match options.expansion {
+            Expansion::Immediate => {
+                let home = match home_dir() {
+                    Some(home) => home,
+                    None => return Err(UnableToFindHomeDirectory),
+                };
+
+                expand(path, Some(&home))
+            }
+            Expansion::Deferred => Ok(parse(path)
+                .into_iter()
+                .map(|segment| match segment {
+                    Segment::Literal(literal) => literal,
+                    Segment::Home => "%USERPROFILE%".to_string(),
+                    Segment::Variable(name) => format!("%{name}%"),
+                })
+                .collect()),
+        }
     }
 
-    /// Determines if the given path exists in the current PATH environment variable.
+    /// Adds the given paths to the PATH at the given position.
     ///
-    /// When comparing the paths, this function will ignore trailing slashes so that
-    /// if this is the only different we don't add the path twice.
-    fn path_exists_in_path(path: &str, path_env: &str) -> bool {
-        let normalized_path = path.trim_end_matches(&['/', '\\'][..]);
+    /// The paths are compared according to the equivalence rules of the
+    /// options, so that differences such as trailing slashes or casing don't
+    /// cause a path to be added twice. The PATH is written to the registry
+    /// once, and the update type of each path is returned in the order of the
+    /// input.
+    fn update(
+        paths: &[&Path],
+        position: &Position,
+        options: &UpdateOptions,
+    ) -> Result<Vec<UpdateType>, PathmanError> {
+        let paths = paths
+            .iter()
+            .map(|path| Self::resolve_path(path, options))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let position = match position {
+            Position::Before { anchor, fallback } => {
+                Position::before(Self::resolve_path(anchor, options)?, *fallback)
+            }
+            Position::After { anchor, fallback } => {
+                Position::after(Self::resolve_path(anchor, options)?, *fallback)
+            }
+            position => position.clone(),
+        };
+
+        let mut list =
+            PathList::parse(&Self::get_path(options)?, ';').equivalence(options.equivalence);
+
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+        let update_types = list.update(&paths, &position, options.move_existing);
+
+        if update_types.iter().all(|u| *u == UpdateType::AlreadyInPath) {
+            return Ok(update_types);
+        }
 
-        path_env.split(';').any(|segment| {
-            let normalized_segment = segment.trim_end_matches(&['/', '\\'][..]);
+        Self::set_path(&list.to_string(), options)?;
+        Ok(update_types)
+    }
+
+    /// Adds a single path to the PATH at the given position.
+    fn update_one(
+        path: &Path,
+        position: &Position,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        let mut update_types = Self::update(&[path], position, options)?;
 
-            normalized_path == normalized_segment
-        })
+        Ok(update_types.remove(0))
     }
 }
 
 impl PathUpdater for WindowsPathUpdater {
-    fn prepend<P: AsRef<Path>>(path: P, _: Option<&str>) -> Result<UpdateType, PathmanError> {
-        let path = path.as_ref().to_string_lossy().to_string();
-        let current_path = Self::get_path()?;
+    fn prepend<P: AsRef<Path>>(
+        path: P,
+        _: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        Self::update_one(path.as_ref(), &Position::Front, options)
+    }
 
-        // Check if the path already exists in PATH
-        if Self::path_exists_in_path(&path, &current_path) {
-            return Ok(UpdateType::AlreadyInPath);
+    fn append<P: AsRef<Path>>(
+        path: P,
+        _: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        Self::update_one(path.as_ref(), &Position::Back, options)
+    }
+
+    fn insert<P: AsRef<Path>>(
+        path: P,
+        position: &Position,
+        _: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        Self::update_one(path.as_ref(), position, options)
+    }
+
+    fn insert_all<P: AsRef<Path>>(
+        paths: &[P],
+        position: &Position,
+        _: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<Vec<UpdateType>, PathmanError> {
+        let paths: Vec<&Path> = paths.iter().map(AsRef::as_ref).collect();
+
+        Self::update(&paths, position, options)
+    }
+
+    fn locate<P: AsRef<Path>>(
+        path: P,
+        options: &UpdateOptions,
+    ) -> Result<Vec<Location>, PathmanError> {
+        let path = Self::resolve_path(path.as_ref(), options)?;
+        let contains_path = |value: &str| {
+            PathList::parse(value, ';')
+                .equivalence(options.equivalence)
+                .contains(&path)
+        };
+
+        let mut locations = Vec::new();
+
+        if contains_path(&Self::get_path(options)?) {
+            locations.push(Location::UserRegistry);
+        }
+        if contains_path(&Self::get_system_path(options)) {
+            locations.push(Location::SystemRegistry);
         }
 
-        // Prepend the new path to the beginning of PATH
-        let new_path = if current_path.is_empty() {
-            path
+        Ok(locations)
+    }
+
+    fn snippet<P: AsRef<Path>>(
+        _paths: &[P],
+        _position: &Position,
+        _comment: Option<&str>,
+        _options: &UpdateOptions,
+    ) -> Result<String, PathmanError> {
+        // The PATH is stored in the registry rather than in a configuration
+        // file the user could edit
+        Err(UnsupportedTarget)
+    }
+
+    fn restore<P: AsRef<Path>>(file: P, options: &UpdateOptions) -> Result<(), PathmanError> {
+        Self::check_target(options)?;
+
+        // The backups are looked up with the default settings when the
+        // options don't enable them
+        let options = match options.backup {
+            Some(_) => options.clone(),
+            None => options.clone().backup(Backup::new()),
+        };
+        let backup = options.backup_settings()?.unwrap_or_default();
+
+        let name = file.as_ref().to_string_lossy().to_string();
+        let Some(last) = backup.list(&Self::backup_file(&name, &options)).pop() else {
+            return Err(NoBackup(name));
+        };
+
+        // The variable wasn't set before the change, so it's deleted again
+        if Backup::is_absent(&last) {
+            if Self::get_value(&name, &options)?.is_some() {
+                Self::delete_value(&name, &options)?;
+            }
         } else {
-            format!("{path};{current_path}")
+            let vtype = last.extension().and_then(|extension| {
+                VALUE_TYPES
+                    .iter()
+                    .find(|vtype| format!("{vtype:?}") == extension.to_string_lossy())
+            });
+
+            let (Some(vtype), Ok(bytes)) = (vtype, std::fs::read(&last)) else {
+                return Err(UnableToReadShellConfigFile(
+                    last.to_string_lossy().to_string(),
+                ));
+            };
+
+            let value = RegValue {
+                bytes: bytes.into(),
+                vtype: vtype.clone(),
+            };
+            Self::set_raw_value(&name, &value, &options)?;
+        }
+
+        match std::fs::remove_file(&last) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(UnableToWriteShellConfigFile(
+                last.to_string_lossy().to_string(),
+            )),
+        }
+    }
+
+    fn activation(options: &UpdateOptions) -> Result<Vec<Activation>, PathmanError> {
+        Self::check_target(options)?;
+
+        let name = Self::value_name(options);
+        let user = match options.registry_key.as_str() {
+            "Environment" => format!("[Environment]::GetEnvironmentVariable('{name}', 'User')"),
+            key => format!(
+                "(Get-ItemProperty -Path 'HKCU:\\{}' -Name '{name}').'{name}'",
+                key.replace('\'', "''")
+            ),
+        };
+
+        // The PATH of a process combines the system-wide and the user's PATH,
+        // while the user's value of the other variables overrides the
+        // system-wide one
+        let value = match name {
+            "Path" => {
+                format!("[Environment]::GetEnvironmentVariable('Path', 'Machine') + ';' + {user}")
+            }
+            _ => user,
         };
 
-        Self::set_path(&new_path)?;
-        Ok(UpdateType::Success)
+        Ok(vec![Activation {
+            shell: "PowerShell".to_string(),
+            command: format!("$env:{name} = {value}"),
+        }])
     }
 
-    fn append<P: AsRef<Path>>(path: P, _: Option<&str>) -> Result<UpdateType, PathmanError> {
-        let path = path.as_ref().to_string_lossy().to_string();
-        let current_path = Self::get_path()?;
+    fn remove<P: AsRef<Path>>(
+        path: P,
+        _: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        let path = Self::resolve_path(path.as_ref(), options)?;
 
-        // Check if the path already exists in PATH
-        if Self::path_exists_in_path(&path, &current_path) {
-            return Ok(UpdateType::AlreadyInPath);
+        let mut list =
+            PathList::parse(&Self::get_path(options)?, ';').equivalence(options.equivalence);
+
+        if !list.remove(&path) {
+            return Ok(UpdateType::NotInPath);
         }
 
-        // Append the new path to the end of PATH
-        let new_path = if current_path.is_empty() {
-            path
-        } else {
-            format!("{current_path};{path}")
+        Self::set_path(&list.to_string(), options)?;
+        Ok(UpdateType::Removed)
+    }
+
+    fn set_var(
+        name: &str,
+        value: &str,
+        _: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        Self::check_target(options)?;
+
+        let value = match options.literal {
+            true => value.to_string(),
+            false => Self::resolve_path(Path::new(value), options)?,
+        };
+
+        let unchanged = |current: &str| match options.literal {
+            true => current == value,
+            false => options.equivalence.equivalent(current, &value),
         };
 
-        Self::set_path(&new_path)?;
-        Ok(UpdateType::Success)
+        let update_type = match Self::get_value(name, options)? {
+            None => UpdateType::Success,
+            Some(current) if unchanged(&current) => return Ok(UpdateType::AlreadyInPath),
+            Some(_) => UpdateType::Replaced,
+        };
+
+        Self::back_up(name, options)?;
+        Self::set_value(name, &value, options.literal, options)?;
+        Ok(update_type)
+    }
+
+    fn unset_var(
+        name: &str,
+        _: Option<&str>,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        Self::check_target(options)?;
+
+        if Self::get_value(name, options)?.is_none() {
+            return Ok(UpdateType::NotInPath);
+        }
+
+        Self::back_up(name, options)?;
+        Self::delete_value(name, options)?;
+        Ok(UpdateType::Removed)
+    }
+
+    fn replace<O: AsRef<Path>, N: AsRef<Path>>(
+        old: O,
+        new: N,
+        options: &UpdateOptions,
+    ) -> Result<UpdateType, PathmanError> {
+        let old = Self::resolve_path(old.as_ref(), options)?;
+        let new = Self::resolve_path(new.as_ref(), options)?;
+
+        let mut list =
+            PathList::parse(&Self::get_path(options)?, ';').equivalence(options.equivalence);
+
+        if !list.contains(&old) {
+            return Self::update_one(Path::new(&new), &Position::Front, options);
+        }
+
+        if options.equivalence.equivalent(&old, &new) {
+            return Ok(UpdateType::AlreadyInPath);
+        }
+
+        // Replace the first occurrence of the old path, preserving its
+        // position, unless the new path is already present
+        list.replace(&old, &new);
+
+        Self::set_path(&list.to_string(), options)?;
+        Ok(UpdateType::Replaced)
     }
 }
@@ -0,0 +1,193 @@
+use crate::error::PathmanError;
+use crate::error::PathmanError::{UnableToConvertPathToString, UnableToFindHomeDirectory};
+use crate::expand::expand;
+use crate::options::UpdateOptions;
+use crate::path_list::PathList;
+use crate::platform::Position;
+use home::home_dir;
+use std::env::var_os;
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use std::process::Command;
+
+/// Expands `~` and environment variables in the given path
+///
+/// The PATH of a process is never expanded, so the expansion option is
+/// ignored.
+fn resolve_path(path: &Path, home: &Path) -> Result<String, PathmanError> {
+    match path.to_str() {
+        Some(p) => expand(p, Some(home)),
+        None => Err(UnableToConvertPathToString),
+    }
+}
+
+/// Parses the given value of the variable of the options into a list
+fn parse(current: &OsStr, home: &Path, options: &UpdateOptions) -> Result<PathList, PathmanError> {
+    let mut list = match current.to_str() {
+        Some(current) => PathList::parse(current, PathList::SEPARATOR)
+            .equivalence(options.equivalence)
+            .home(Some(home)),
+        None => return Err(UnableToConvertPathToString),
+    };
+
+    // An unset MANPATH stands for the default search path, which is kept as
+    // an empty entry
+    if options.variable == "MANPATH" && list.is_empty() {
+        list.insert("", &Position::Back);
+    }
+
+    Ok(list)
+}
+
+/// Computes the value of the PATH after adding the given paths at the given
+/// position.
+///
+/// Paths that are already present are left where they are, unless the
+/// `move_existing` option is set.
+pub(crate) fn add_paths(
+    current: &OsStr,
+    paths: &[&Path],
+    position: &Position,
+    options: &UpdateOptions,
+) -> Result<OsString, PathmanError> {
+    let home = match home_dir() {
+        Some(home) => home,
+        None => return Err(UnableToFindHomeDirectory),
+    };
+
+    let paths = paths
+        .iter()
+        .map(|path| resolve_path(path, &home))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let position = match position {
+        Position::Before { anchor, fallback } => {
+            Position::before(resolve_path(anchor, &home)?, *fallback)
+        }
+        Position::After { anchor, fallback } => {
+            Position::after(resolve_path(anchor, &home)?, *fallback)
+        }
+        position => position.clone(),
+    };
+
+    let mut list = parse(current, &home, options)?;
+
+    let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+    list.update(&paths, &position, options.move_existing);
+
+    Ok(OsString::from(list.to_string()))
+}
+
+/// Computes the value of the PATH after replacing the old path with the new
+/// path.
+///
+/// The new path takes the position of the first occurrence of the old path,
+/// or is prepended if the old path is not present.
+pub(crate) fn replace_path(
+    current: &OsStr,
+    old: &Path,
+    new: &Path,
+    options: &UpdateOptions,
+) -> Result<OsString, PathmanError> {
+    let home = match home_dir() {
+        Some(home) => home,
+        None => return Err(UnableToFindHomeDirectory),
+    };
+
+    let old = resolve_path(old, &home)?;
+    let new = resolve_path(new, &home)?;
+
+    let mut list = parse(current, &home, options)?;
+
+    if !list.replace(&old, &new) {
+        list.insert(&new, &Position::Front);
+    }
+
+    Ok(OsString::from(list.to_string()))
+}
+
+/// Computes the value of the PATH after removing the given path.
+pub(crate) fn remove_path(
+    current: &OsStr,
+    path: &Path,
+    options: &UpdateOptions,
+) -> Result<OsString, PathmanError> {
+    let home = match home_dir() {
+        Some(home) => home,
+        None => return Err(UnableToFindHomeDirectory),
+    };
+
+    let mut list = parse(current, &home, options)?;
+    list.remove(&resolve_path(path, &home)?);
+
+    Ok(OsString::from(list.to_string()))
+}
+
+/// Applies the given update to the PATH of the current process, if the
+/// `update_process` option is set.
+pub(crate) fn update_process<F>(options: &UpdateOptions, update: F) -> Result<(), PathmanError>
+where
+    F: FnOnce(&OsStr) -> Result<OsString, PathmanError>,
+{
+    if !options.update_process {
+        return Ok(());
+    }
+
+    let current = var_os(&options.variable).unwrap_or_default();
+    let path = update(&current)?;
+
+    // SAFETY: the option can only be set through the unsafe
+    // `UpdateOptions::update_process`, whose caller guarantees that no other
+    // thread accesses the environment
+    unsafe { std::env::set_var(&options.variable, path) };
+
+    Ok(())
+}
+
+/// Returns the value the given variable of the current process is set to
+///
+/// The environment of a process is never expanded, so `~` and environment
+/// variables are expanded unless the `literal` option is set. The value is
+/// returned as is when the `update_process` option isn't set, as it isn't
+/// used then.
+pub(crate) fn resolve_value(value: &str, options: &UpdateOptions) -> Result<String, PathmanError> {
+    if !options.update_process || options.literal {
+        return Ok(value.to_string());
+    }
+
+    expand(value, home_dir().as_deref())
+}
+
+/// Sets the given variable of the current process to the given resolved
+/// value, or unsets it, if the `update_process` option is set.
+pub(crate) fn set_process_var(options: &UpdateOptions, name: &str, value: Option<&str>) {
+    if !options.update_process {
+        return;
+    }
+
+    // SAFETY: the option can only be set through the unsafe
+    // `UpdateOptions::update_process`, whose caller guarantees that no other
+    // thread accesses the environment
+    match value {
+        Some(value) => unsafe { std::env::set_var(name, value) },
+        None => unsafe { std::env::remove_var(name) },
+    }
+}
+
+/// Returns the value of the given variable that a child process spawned by
+/// the given command would get.
+///
+/// The value set on the command is used if any, and the value of the current
+/// process otherwise.
+pub(crate) fn command_path(command: &Command, variable: &str) -> OsString {
+    // Environment variable names are case-insensitive on Windows
+    let is_variable = |key: &OsStr| match cfg!(windows) {
+        true => key.eq_ignore_ascii_case(variable),
+        false => key == variable,
+    };
+
+    match command.get_envs().find(|(key, _)| is_variable(key)) {
+        Some((_, value)) => value.map(OsStr::to_os_string).unwrap_or_default(),
+        None => var_os(variable).unwrap_or_default(),
+    }
+}
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+/// Where a directory is found in the PATH environment variable
+///
+/// A directory can be configured to be added to the PATH without being in the
+/// PATH of the current process yet, for example when the shell configuration
+/// file was updated after the shell was started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathStatus {
+    /// Whether the directory is in the PATH of the current process.
+    pub in_process: bool,
+
+    /// The places where the directory is configured to be added to the PATH.
+    pub locations: Vec<Location>,
+}
+
+/// A place where a directory is configured to be added to the PATH
+/// environment variable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    /// A line of a shell configuration file, numbered from 1.
+    ConfigFile { path: PathBuf, line: usize },
+
+    /// The user's `Path` value in the Windows registry.
+    UserRegistry,
+
+    /// The system-wide `Path` value in the Windows registry.
+    SystemRegistry,
+}
+
+impl PathStatus {
+    /// Returns whether the directory is configured to be added to the PATH
+    pub fn is_configured(&self) -> bool {
+        !self.locations.is_empty()
+    }
+
+    /// Returns whether the directory is configured to be added to the PATH,
+    /// but the shell needs to be restarted for it to be in the PATH
+    pub fn requires_restart(&self) -> bool {
+        self.is_configured() && !self.in_process
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+
+/// The files updated to persist a change to the PATH environment variable
+///
+/// By default, the configuration file of the current shell is updated, which
+/// only affects the shells started afterwards. Other targets make the change
+/// visible to other kinds of sessions.
+///
+/// Targets other than [`Target::ShellConfig`] are only supported on macOS and
+/// Linux.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Target {
+    /// The configuration file of the current shell (e.g., `~/.bashrc`), or
+    /// the user's environment in the registry on Windows.
+    #[default]
+    ShellConfig,
+
+    /// A systemd `environment.d` file of the user, read by the systemd user
+    /// manager when the user logs in.
+    ///
+    /// The file is written to
+    /// `$XDG_CONFIG_HOME/environment.d/<name>.conf`, which defaults to
+    /// `~/.config/environment.d/<name>.conf`, so that applications started
+    /// from graphical launchers and systemd user services see the change. The
+    /// files are read in lexical order, so the name is usually prefixed with
+    /// a number (e.g., `50-biome`).
+    EnvironmentD { name: String },
+
+    /// A system-wide profile script, read by the login shells of all users.
+    ///
+    /// The change is written to `/etc/profile.d/<name>.sh` for POSIX shells,
+    /// and to `/usr/share/fish/vendor_conf.d/<name>.fish` for fish. Both files
+    /// are located under the root directory of the options, and writing them
+    /// usually requires root privileges. Paths starting with `~` or `$HOME`
+    /// are written relative to `$HOME`, so that they refer to the home
+    /// directory of each user rather than to that of the installer.
+    ProfileD { name: String },
+
+    /// The system-wide `/etc/environment` file, read by pam_env for every
+    /// login, including graphical and non-shell sessions such as SFTP.
+    ///
+    /// The directory is spliced into the existing assignment of the variable
+    /// (e.g., `PATH="/usr/local/bin:/usr/bin"`), leaving the other lines
+    /// untouched. pam_env doesn't expand variables in this file, so the paths
+    /// are always expanded and written literally, and the assignment of the
+    /// PATH starts from the default PATH when the file doesn't set it. The
+    /// file is located under the root directory of the options.
+    Environment,
+
+    /// A system-wide `paths.d` file, read by `path_helper` on macOS when a
+    /// login shell starts.
+    ///
+    /// The paths are written to `/etc/paths.d/<name>`, one per line, or to
+    /// `/etc/manpaths.d/<name>` when the MANPATH is updated. `path_helper`
+    /// adds these entries after the ones of `/etc/paths`, so the position
+    /// only orders the entries of the file. Paths are always expanded and
+    /// written literally, comments are not written, and the file is deleted
+    /// when its last entry is removed. The file is located under the root
+    /// directory of the options.
+    PathsD { name: String },
+
+    /// The shell configuration files of a skeleton directory, such as
+    /// `/etc/skel`, which are copied to the home directory of new users.
+    ///
+    /// The change is written to the `.bashrc`, `.zshrc` and
+    /// `.config/fish/config.fish` files of the directory, which are created
+    /// if they don't exist yet, so that new users inherit it regardless of
+    /// their shell. The skeleton directory stands for the home directory of
+    /// the new users, so `~` refers to it and paths located in it are written
+    /// relative to `$HOME`. The directory is located under the root directory
+    /// of the options.
+    Skeleton { dir: PathBuf },
+}
+
+impl Target {
+    /// Creates a target writing the systemd `environment.d` file of the given
+    /// name
+    pub fn environment_d(name: &str) -> Self {
+        Target::EnvironmentD {
+            name: name.to_string(),
+        }
+    }
+
+    /// Creates a target writing the `paths.d` file of the given name
+    pub fn paths_d(name: &str) -> Self {
+        Target::PathsD {
+            name: name.to_string(),
+        }
+    }
+
+    /// Creates a target writing the shell configuration files of the given
+    /// skeleton directory (e.g., `/etc/skel`)
+    pub fn skeleton<P: AsRef<Path>>(dir: P) -> Self {
+        Target::Skeleton {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Creates a target writing the system-wide profile scripts of the given
+    /// name
+    pub fn profile_d(name: &str) -> Self {
+        Target::ProfileD {
+            name: name.to_string(),
+        }
+    }
+}
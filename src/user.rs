@@ -0,0 +1,190 @@
+#[cfg(unix)]
+use crate::error::PathmanError;
+#[cfg(unix)]
+use crate::error::PathmanError::UnknownUser;
+#[cfg(unix)]
+use std::ffi::{CStr, CString, OsStr};
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+
+/// The user whose shell configuration files are updated
+///
+/// By default, the files of the current user are updated. When running
+/// under `sudo`, the current user is `root`, so installers usually want to
+/// update the files of the user who invoked `sudo` instead.
+///
+/// The home directory and the login shell of other users are read from the
+/// user database of the system, which includes the users of other sources
+/// such as LDAP. Under a root directory other than `/`, they are read from
+/// the `/etc/passwd` file of the root directory instead, in which case users
+/// only known to other sources are not found, and the home directory and the
+/// shell can be set explicitly.
+///
+/// Users other than [`User::Current`] are only supported on macOS and Linux.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum User {
+    /// The user running the current process
+    #[default]
+    Current,
+
+    /// The user who invoked `sudo` or `pkexec`, as told by the `SUDO_USER` or
+    /// `PKEXEC_UID` environment variables, or the current user when neither
+    /// is set
+    Invoking,
+
+    /// The user of the given name
+    Name(String),
+
+    /// The user of the given UID
+    Uid(u32),
+}
+
+impl User {
+    /// Creates a user from the given name
+    pub fn name(name: &str) -> Self {
+        User::Name(name.to_string())
+    }
+}
+
+/// An entry of the passwd database
+#[cfg(unix)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Account {
+    pub uid: u32,
+    pub gid: u32,
+    pub home: PathBuf,
+    pub shell: String,
+}
+
+#[cfg(unix)]
+impl Account {
+    /// Parses a line of the passwd database, using the
+    /// `name:password:uid:gid:gecos:home:shell` format
+    fn parse(line: &str) -> Option<(&str, Self)> {
+        let fields: Vec<&str> = line.split(':').collect();
+        let [name, _, uid, gid, _, home, shell] = fields[..] else {
+            return None;
+        };
+
+        Some((
+            name,
+            Self {
+                uid: uid.parse().ok()?,
+                gid: gid.parse().ok()?,
+                home: PathBuf::from(home),
+                shell: shell.to_string(),
+            },
+        ))
+    }
+
+    /// Looks up the account of the user of the given name or UID in the user
+    /// database of the system, through the name service switch
+    fn lookup(name: Option<&str>, uid: Option<u32>) -> Option<Self> {
+        let name = name.map(CString::new).transpose().ok()?;
+
+        // SAFETY: the passwd structure is plain data, filled by the lookup
+        // functions with pointers into the buffer, which outlives them
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut buffer: Vec<libc::c_char> = vec![0; 1024];
+        let mut result = std::ptr::null_mut();
+
+        loop {
+            let code = match (&name, uid) {
+                (Some(name), _) => unsafe {
+                    libc::getpwnam_r(
+                        name.as_ptr(),
+                        &mut passwd,
+                        buffer.as_mut_ptr(),
+                        buffer.len(),
+                        &mut result,
+                    )
+                },
+                (None, Some(uid)) => unsafe {
+                    libc::getpwuid_r(
+                        uid,
+                        &mut passwd,
+                        buffer.as_mut_ptr(),
+                        buffer.len(),
+                        &mut result,
+                    )
+                },
+                (None, None) => return None,
+            };
+
+            // The buffer is grown until the entry fits
+            match code {
+                libc::ERANGE => buffer.resize(buffer.len() * 2, 0),
+                _ => break,
+            }
+        }
+
+        if result.is_null() {
+            return None;
+        }
+
+        // SAFETY: the lookup succeeded, so the strings of the entry are valid
+        // and terminated by a null byte
+        let (home, shell) = unsafe {
+            (
+                CStr::from_ptr(passwd.pw_dir),
+                CStr::from_ptr(passwd.pw_shell),
+            )
+        };
+
+        Some(Self {
+            uid: passwd.pw_uid,
+            gid: passwd.pw_gid,
+            home: PathBuf::from(OsStr::from_bytes(home.to_bytes())),
+            shell: shell.to_string_lossy().to_string(),
+        })
+    }
+
+    /// Reads the account of the user of the given name or UID from the
+    /// `/etc/passwd` file under the given root directory
+    fn read(root: &Path, name: Option<&str>, uid: Option<u32>) -> Option<Self> {
+        let content = std::fs::read_to_string(root.join("etc/passwd")).unwrap_or_default();
+
+        content
+            .lines()
+            .filter_map(Account::parse)
+            .find(|(n, account)| name == Some(*n) || uid == Some(account.uid))
+            .map(|(_, account)| account)
+    }
+
+    /// Finds the account of the given user in the user database of the
+    /// system, or in the passwd file under the given root directory when it
+    /// isn't `/`
+    ///
+    /// Returns `None` when the user is the current user.
+    pub fn find(user: &User, root: &Path) -> Result<Option<Self>, PathmanError> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        // Users are looked up either by name or by UID
+        let (name, uid) = match user {
+            User::Current => return Ok(None),
+            User::Invoking => match (var("SUDO_USER"), var("PKEXEC_UID")) {
+                (Some(name), _) => (Some(name), None),
+                (None, Some(uid)) => match uid.parse() {
+                    Ok(uid) => (None, Some(uid)),
+                    Err(_) => return Err(UnknownUser(uid)),
+                },
+                (None, None) => return Ok(None),
+            },
+            User::Name(name) => (Some(name.clone()), None),
+            User::Uid(uid) => (None, Some(*uid)),
+        };
+
+        let account = match root == Path::new("/") {
+            true => Self::lookup(name.as_deref(), uid),
+            false => Self::read(root, name.as_deref(), uid),
+        };
+
+        match (account, name, uid) {
+            (Some(account), _, _) => Ok(Some(account)),
+            (None, Some(name), _) => Err(UnknownUser(name)),
+            (None, None, uid) => Err(UnknownUser(uid.unwrap_or_default().to_string())),
+        }
+    }
+}
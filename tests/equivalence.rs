@@ -0,0 +1,72 @@
+use pathman::Equivalence;
+
+#[test]
+fn it_considers_entries_with_trailing_and_repeated_separators_equivalent() {
+    let equivalence = Equivalence::new();
+
+    assert!(equivalence.equivalent("/usr/local/bin", "/usr/local/bin/"));
+    assert!(equivalence.equivalent("/usr/local/bin", "/usr/local//bin"));
+    assert!(equivalence.equivalent("/usr/local/bin", "/usr/local/./bin"));
+    assert!(equivalence.equivalent("/usr/local/bin", "/usr/local/lib/../bin"));
+    assert!(!equivalence.equivalent("/usr/local/bin", "/usr/bin"));
+}
+
+#[test]
+fn it_keeps_leading_parent_directories_of_relative_entries() {
+    let equivalence = Equivalence::new();
+
+    assert!(equivalence.equivalent("../../bin", "../lib/../../bin"));
+    assert!(!equivalence.equivalent("../..", "bin/.."));
+    assert!(!equivalence.equivalent("../..", ".."));
+    assert!(!equivalence.equivalent("../../bin", "bin"));
+    assert!(equivalence.equivalent("/../usr/bin", "/usr/bin"));
+}
+
+#[test]
+fn it_considers_entries_with_expanded_variables_equivalent() {
+    temp_env::with_vars([("PATHMAN_TEST_DIR", Some("/opt/test"))], || {
+        let equivalence = Equivalence::new();
+
+        assert!(equivalence.equivalent("$PATHMAN_TEST_DIR/bin", "/opt/test/bin"));
+        assert!(equivalence.equivalent("${PATHMAN_TEST_DIR}/bin", "/opt/test/bin"));
+        assert!(
+            !equivalence
+                .expand(false)
+                .equivalent("$PATHMAN_TEST_DIR/bin", "/opt/test/bin")
+        );
+    });
+}
+
+#[test]
+fn it_considers_entries_differing_in_case_or_normalization_form_equivalent_if_case_insensitive() {
+    let equivalence = Equivalence::new().case_insensitive(true);
+
+    assert!(equivalence.equivalent("/Applications/Tool", "/applications/tool"));
+    assert!(equivalence.equivalent("/opt/caf\u{e9}", "/opt/cafe\u{301}"));
+    assert!(
+        !equivalence
+            .case_insensitive(false)
+            .equivalent("/Applications/Tool", "/applications/tool")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn it_considers_symlinks_equivalent_to_their_target_if_canonicalizing() {
+    let temp = assert_fs::TempDir::new().unwrap();
+
+    let target = temp.path().join("bin");
+    let link = temp.path().join("link");
+    std::fs::create_dir(&target).unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    let target = target.to_string_lossy().to_string();
+    let link = link.to_string_lossy().to_string();
+
+    assert!(!Equivalence::new().equivalent(&target, &link));
+    assert!(
+        Equivalence::new()
+            .canonicalize(true)
+            .equivalent(&target, &link)
+    );
+}
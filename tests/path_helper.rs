@@ -0,0 +1,50 @@
+use pathman::PathHelper;
+
+#[test]
+fn it_puts_the_system_entries_before_the_current_path() {
+    let helper = PathHelper::parse(
+        "/usr/local/bin\n/usr/bin\n/bin\n",
+        &["/opt/a/bin\n", "\n/opt/b/bin\n/usr/bin\n"],
+    );
+
+    assert_eq!(
+        vec![
+            "/usr/local/bin",
+            "/usr/bin",
+            "/bin",
+            "/opt/a/bin",
+            "/opt/b/bin"
+        ],
+        helper.entries()
+    );
+    assert_eq!(
+        "/usr/local/bin:/usr/bin:/bin:/opt/a/bin:/opt/b/bin:/Users/me/bin",
+        helper.apply("/Users/me/bin:/usr/bin::/bin")
+    );
+}
+
+#[test]
+fn it_detects_prepended_directories_that_would_be_reordered() {
+    let helper = PathHelper::parse("/usr/bin\n/bin\n", &[]);
+
+    assert!(helper.reorders("/Users/me/bin", "/usr/bin:/bin"));
+    assert!(!helper.reorders("/usr/bin", "/bin"));
+    assert!(!PathHelper::parse("", &[]).reorders("/Users/me/bin", "/usr/bin"));
+}
+
+#[test]
+fn it_reads_the_system_entries_from_the_root_directory() {
+    let root = assert_fs::TempDir::new().unwrap();
+    std::fs::create_dir_all(root.path().join("etc/paths.d")).unwrap();
+    std::fs::write(root.path().join("etc/paths"), "/usr/bin\n/bin\n").unwrap();
+    std::fs::write(root.path().join("etc/paths.d/20-b"), "/opt/b/bin\n").unwrap();
+    std::fs::write(root.path().join("etc/paths.d/10-a"), "/opt/a/bin\n").unwrap();
+
+    assert_eq!(
+        vec!["/usr/bin", "/bin", "/opt/a/bin", "/opt/b/bin"],
+        PathHelper::read(root.path()).unwrap().entries()
+    );
+
+    let empty = assert_fs::TempDir::new().unwrap();
+    assert!(PathHelper::read(empty.path()).unwrap().entries().is_empty());
+}
@@ -0,0 +1,62 @@
+use pathman::{Equivalence, Fallback, PathList, Position};
+
+#[test]
+fn it_parses_and_serializes_a_path_list_preserving_empty_entries() {
+    let list = PathList::parse("/usr/bin::/bin:", ':');
+
+    assert_eq!(vec!["/usr/bin", "", "/bin", ""], list.entries());
+    assert_eq!("/usr/bin::/bin:", list.to_string());
+
+    assert!(PathList::parse("", ';').is_empty());
+    assert_eq!(
+        vec![r"C:\Tools", r"%USERPROFILE%\bin"],
+        PathList::parse(r"C:\Tools;%USERPROFILE%\bin", ';').entries()
+    );
+}
+
+#[test]
+fn it_inserts_entries_at_the_given_position() {
+    let mut list = PathList::parse("/usr/bin:/bin", ':');
+
+    list.insert("/front", &Position::Front);
+    list.insert("/back", &Position::Back);
+    list.insert_all(&["/a", "/b"], &Position::before("/bin/", Fallback::Front));
+    list.insert("/c", &Position::after("/missing", Fallback::Back));
+
+    assert_eq!("/front:/usr/bin:/a:/b:/bin:/back:/c", list.to_string());
+    assert!(list.is_at("/b", &Position::before("/bin", Fallback::Front)));
+    assert!(list.is_at("/c/", &Position::after("/missing", Fallback::Back)));
+    assert!(!list.is_at("/a", &Position::Front));
+}
+
+#[test]
+fn it_removes_moves_and_replaces_equivalent_entries() {
+    let mut list = PathList::parse("/a:/usr/bin:/a/:/bin", ':');
+
+    assert!(list.contains("/a//"));
+    assert!(list.move_to("/a", &Position::Back));
+    assert_eq!("/usr/bin:/bin:/a", list.to_string());
+
+    assert!(list.replace("/usr/bin/", "/opt/bin"));
+    assert_eq!("/opt/bin:/bin:/a", list.to_string());
+    assert!(!list.replace("/missing", "/opt/bin"));
+
+    assert!(list.remove("/bin"));
+    assert!(!list.remove("/bin"));
+    assert_eq!("/opt/bin:/a", list.to_string());
+}
+
+#[test]
+fn it_dedupes_entries_according_to_the_equivalence_rules() {
+    let mut list = PathList::parse("/Tools:/bin:/tools:/bin/", ':')
+        .equivalence(Equivalence::new().case_insensitive(true));
+    list.dedupe();
+
+    assert_eq!("/Tools:/bin", list.to_string());
+
+    let mut list = PathList::parse("/Tools:/bin:/tools:/bin/", ':')
+        .equivalence(Equivalence::new().case_insensitive(false));
+    list.dedupe();
+
+    assert_eq!("/Tools:/bin:/tools", list.to_string());
+}
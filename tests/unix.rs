@@ -2,13 +2,23 @@
 
 use assert_fs::prelude::*;
 use pathman::PathmanError::{
-    UnableToFindShellConfigFile, UnableToReadShellConfigFile, UnableToWriteShellConfigFile,
+    InsufficientPermissions, InvalidVariableName, ManagedConfigFile, NoBackup, UnableToDetectShell,
+    UnableToFindHomeDirectory, UnableToFindShellConfigFile, UnableToReadShellConfigFile,
+    UnableToRemoveEntry, UnableToWriteShellConfigFile, UndefinedVariable, UnknownUser,
+};
+use pathman::{
+    Activation, Backup, Equivalence, Expansion, Fallback, Location, Position, Target,
+    UpdateOptions, UpdateType, User, WriteStrategy, WrittenFile, activation_commands,
+    add_all_to_path, add_to_command_path, add_to_path, append_to_path, append_to_path_with_options,
+    find_in_path, path_snippet, prepend_to_path, prepend_to_path_with_options, remove_from_path,
+    replace_in_path, restore_backup, set_env_var, unset_env_var,
 };
-use pathman::{UpdateType, append_to_path, prepend_to_path};
 use predicates::prelude::*;
 use std::fs::{Permissions, create_dir_all, set_permissions};
-use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
 
 // --- Bash ---
 
@@ -1181,3 +1191,3941 @@ fn it_does_not_append_a_path_to_the_path_if_shell_is_not_supported() {
         },
     );
 }
+
+// --- Existence check ---
+
+#[test]
+fn it_wraps_the_export_command_in_an_existence_check_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().only_if_exists(true);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options(PathBuf::from("/test"), None, &options)
+            );
+            assert!(
+                predicate::str::contains("[ -d \"/test\" ] && export PATH=\"/test:$PATH\"")
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(bashrc.path())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_wraps_the_set_command_in_an_existence_check_in_fish_config() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .config/fish/config.fish file in the virtual home directory
+    let fish_config = home.child(".config/fish/config.fish");
+    create_dir_all(fish_config.parent().unwrap()).unwrap();
+    fish_config.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/fish".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().only_if_exists(true);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options(PathBuf::from("/test"), None, &options)
+            );
+            assert!(
+                predicate::str::contains("test -d \"/test\"; and set -gx PATH \"/test\" $PATH")
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(fish_config.path())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_does_not_add_a_guarded_line_if_the_export_command_is_already_present_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory with an existing export command
+    let bashrc = home.child(".bashrc");
+    bashrc.write_str("export PATH=\"/test:$PATH\"\n").unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().only_if_exists(true);
+
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options(PathBuf::from("/test"), None, &options)
+            );
+            assert!(
+                predicate::str::contains("[ -d")
+                    .not()
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(bashrc.path())
+            );
+        },
+    );
+}
+
+// --- Home-relative paths ---
+
+#[test]
+fn it_writes_paths_in_the_home_directory_relative_to_home_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path(home.path().join(".biome/bin"), None)
+            );
+            assert!(
+                predicate::str::contains("export PATH=\"$HOME/.biome/bin:$PATH\"")
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(bashrc.path())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_writes_paths_in_the_home_directory_relative_to_home_in_fish_config() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .config/fish/config.fish file in the virtual home directory
+    let fish_config = home.child(".config/fish/config.fish");
+    create_dir_all(fish_config.parent().unwrap()).unwrap();
+    fish_config.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/fish".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Success),
+                append_to_path(home.path().join(".biome/bin"), None)
+            );
+            assert!(
+                predicate::str::contains("set -gx PATH $PATH \"$HOME/.biome/bin\"")
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(fish_config.path())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_writes_expanded_paths_in_the_home_directory_when_home_relative_is_disabled() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .zshrc file in the virtual home directory
+    let zshrc = home.child(".zshrc");
+    zshrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().home_relative(false);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options(home.path().join(".biome/bin"), None, &options)
+            );
+            assert!(
+                predicate::str::contains(format!(
+                    "export PATH=\"{}/.biome/bin:$PATH\"",
+                    home.path().display()
+                ))
+                .from_utf8()
+                .from_file_path()
+                .eval(zshrc.path())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_does_not_prepend_a_path_in_the_home_directory_if_the_expanded_form_is_already_present() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory with an expanded export command
+    let bashrc = home.child(".bashrc");
+    bashrc
+        .write_str(&format!(
+            "export PATH=\"{}/.biome/bin:$PATH\"\n",
+            home.path().display()
+        ))
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path(home.path().join(".biome/bin"), None)
+            );
+            assert!(
+                predicate::str::contains("$HOME/.biome/bin")
+                    .not()
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(bashrc.path())
+            );
+        },
+    );
+}
+
+// --- Expansion ---
+
+#[test]
+fn it_expands_the_tilde_in_the_path() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().home_relative(false);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("~/.local/bin", None, &options)
+            );
+            assert!(
+                predicate::str::contains(format!(
+                    "export PATH=\"{}/.local/bin:$PATH\"",
+                    home.path().display()
+                ))
+                .from_utf8()
+                .from_file_path()
+                .eval(bashrc.path())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_expands_environment_variables_in_the_path() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("PATHMAN_TEST_DIR", Some("/opt/test".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Success),
+                append_to_path("$PATHMAN_TEST_DIR/bin:${PATHMAN_TEST_DIR}/sbin", None)
+            );
+            assert!(
+                predicate::str::contains("export PATH=\"$PATH:/opt/test/bin:/opt/test/sbin\"")
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(bashrc.path())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_does_not_prepend_a_path_referencing_an_undefined_variable() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("PATHMAN_UNDEFINED", None),
+        ],
+        || {
+            assert_eq!(
+                Err(UndefinedVariable("PATHMAN_UNDEFINED".to_string())),
+                prepend_to_path("${PATHMAN_UNDEFINED}/bin", None)
+            );
+            assert!(
+                predicate::str::is_empty()
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(bashrc.path())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_preserves_the_tilde_and_variables_in_bashrc_when_expansion_is_deferred() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("PATHMAN_UNDEFINED", None),
+        ],
+        || {
+            let options = UpdateOptions::new().expansion(Expansion::Deferred);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("~/.local/bin", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("$PATHMAN_UNDEFINED/bin", None, &options)
+            );
+            assert!(
+                predicate::str::contains("export PATH=\"$HOME/.local/bin:$PATH\"")
+                    .and(predicate::str::contains(
+                        "export PATH=\"${PATHMAN_UNDEFINED}/bin:$PATH\""
+                    ))
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(bashrc.path())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_preserves_the_tilde_and_variables_in_fish_config_when_expansion_is_deferred() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .config/fish/config.fish file in the virtual home directory
+    let fish_config = home.child(".config/fish/config.fish");
+    create_dir_all(fish_config.parent().unwrap()).unwrap();
+    fish_config.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/fish".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().expansion(Expansion::Deferred);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("~/.local/bin", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("${XDG_DATA_HOME}_app/bin", None, &options)
+            );
+            assert!(
+                predicate::str::contains("set -gx PATH \"$HOME/.local/bin\" $PATH")
+                    .and(predicate::str::contains(
+                        "set -gx PATH \"$XDG_DATA_HOME\"\"_app/bin\" $PATH"
+                    ))
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(fish_config.path())
+            );
+        },
+    );
+}
+
+// --- Equivalence ---
+
+#[test]
+fn it_does_not_prepend_a_path_if_an_equivalent_path_is_already_present_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory with an equivalent export command
+    let bashrc = home.child(".bashrc");
+    bashrc
+        .write_str("export PATH=\"/usr/local//bin/:$PATH\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path("/usr/local/bin", None)
+            );
+        },
+    );
+}
+
+#[test]
+fn it_does_not_append_a_path_if_it_is_already_present_relative_to_home_in_fish_config() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .config/fish/config.fish file with a home-relative set command
+    let fish_config = home.child(".config/fish/config.fish");
+    create_dir_all(fish_config.parent().unwrap()).unwrap();
+    fish_config
+        .write_str("test -d \"$HOME/bin\"; and set -gx PATH $PATH \"$HOME/bin\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/fish".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().home_relative(false);
+
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                append_to_path_with_options(home.path().join("bin"), None, &options)
+            );
+        },
+    );
+}
+
+#[test]
+fn it_does_not_prepend_a_symlink_to_a_path_already_present_in_zshrc_if_canonicalizing() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create a directory and a symlink pointing to it
+    let target = home.child("target");
+    target.create_dir_all().unwrap();
+    let link = home.child("link");
+    link.symlink_to_dir(target.path()).unwrap();
+
+    // Create .zshrc file in the virtual home directory with an export command for the target
+    let zshrc = home.child(".zshrc");
+    zshrc
+        .write_str(&format!(
+            "export PATH=\"{}:$PATH\"\n",
+            target.path().display()
+        ))
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().equivalence(Equivalence::new().canonicalize(true));
+
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options(link.path(), None, &options)
+            );
+            assert_eq!(Ok(UpdateType::Success), prepend_to_path(link.path(), None));
+        },
+    );
+}
+
+// --- Position ---
+
+#[test]
+fn it_does_not_prepend_a_path_if_it_is_already_appended_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory with an existing append command
+    let bashrc = home.child(".bashrc");
+    bashrc.write_str("export PATH=\"$PATH:/test\"\n").unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path(PathBuf::from("/test"), None)
+            );
+            assert!(
+                predicate::str::contains("export PATH=\"/test:$PATH\"")
+                    .not()
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(bashrc.path())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_moves_an_appended_path_to_the_front_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory with an existing append command
+    let bashrc = home.child(".bashrc");
+    bashrc
+        .write_str("alias ll='ls -l'\n\n# Test\nexport PATH=\"$PATH:/test\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().move_existing(true);
+
+            assert_eq!(
+                Ok(UpdateType::Moved),
+                prepend_to_path_with_options(PathBuf::from("/test"), Some("Test"), &options)
+            );
+            assert_eq!(
+                "alias ll='ls -l'\n\n# Test\nexport PATH=\"/test:$PATH\"",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_does_not_move_a_path_already_at_the_back_in_zshrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .zshrc file in the virtual home directory with an existing append command
+    let zshrc = home.child(".zshrc");
+    zshrc.write_str("export PATH=\"$PATH:/test\"\n").unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().move_existing(true);
+
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                append_to_path_with_options(PathBuf::from("/test"), None, &options)
+            );
+            assert_eq!(
+                "export PATH=\"$PATH:/test\"\n",
+                std::fs::read_to_string(zshrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_moves_an_appended_path_to_the_front_in_fish_config() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .config/fish/config.fish file with an existing fish_add_path command
+    let fish_config = home.child(".config/fish/config.fish");
+    create_dir_all(fish_config.parent().unwrap()).unwrap();
+    fish_config
+        .write_str("fish_add_path --append /test\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/fish".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().move_existing(true);
+
+            assert_eq!(
+                Ok(UpdateType::Moved),
+                prepend_to_path_with_options(PathBuf::from("/test"), None, &options)
+            );
+            assert!(
+                predicate::str::contains("set -gx PATH \"/test\" $PATH")
+                    .and(predicate::str::contains("fish_add_path").not())
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(fish_config.path())
+            );
+        },
+    );
+}
+
+// --- Anchors ---
+
+#[test]
+fn it_inserts_a_path_before_an_anchor_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let position = Position::before("/usr/bin", Fallback::Front);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                add_to_path("/test", &position, None, &UpdateOptions::new())
+            );
+            assert!(
+                predicate::str::contains(
+                    "case \":$PATH:\" in *:\"/usr/bin\":*) PATH=\":$PATH:\"; \
+                     PATH=\"${PATH%%:\"/usr/bin\":*}:/test:/usr/bin:${PATH#*:\"/usr/bin\":}\"; \
+                     PATH=\"${PATH#:}\"; export PATH=\"${PATH%:}\" ;; \
+                     *) export PATH=\"/test:$PATH\" ;; esac"
+                )
+                .from_utf8()
+                .from_file_path()
+                .eval(bashrc.path())
+            );
+
+            // The path is spliced into the PATH when the shell starts (the line is
+            // POSIX-compatible, so any shell can be used to check it)
+            let output = std::process::Command::new("/bin/sh")
+                .arg("-c")
+                .arg(format!(". {}; echo \"$PATH\"", bashrc.path().display()))
+                .env("PATH", "/a:/usr/bin:/b")
+                .output()
+                .unwrap();
+            assert_eq!(
+                "/a:/test:/usr/bin:/b\n",
+                String::from_utf8_lossy(&output.stdout)
+            );
+
+            // The path falls back to the front when the anchor is absent
+            let output = std::process::Command::new("/bin/sh")
+                .arg("-c")
+                .arg(format!(". {}; echo \"$PATH\"", bashrc.path().display()))
+                .env("PATH", "/a:/b")
+                .output()
+                .unwrap();
+            assert_eq!("/test:/a:/b\n", String::from_utf8_lossy(&output.stdout));
+        },
+    );
+}
+
+#[test]
+fn it_matches_an_anchor_with_glob_characters_literally_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let position = Position::after("/opt/a?b", Fallback::Back);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                add_to_path("/test", &position, None, &UpdateOptions::new())
+            );
+
+            // The anchor doesn't match the entries its glob characters would
+            for shell in ["/bin/sh", "/bin/bash"] {
+                let output = std::process::Command::new(shell)
+                    .arg("-c")
+                    .arg(format!(". {}; echo \"$PATH\"", bashrc.path().display()))
+                    .env("PATH", "/a:/opt/a*b:/opt/a1b:/opt/a?b:/b")
+                    .output()
+                    .unwrap();
+                assert_eq!(
+                    "/a:/opt/a*b:/opt/a1b:/opt/a?b:/test:/b\n",
+                    String::from_utf8_lossy(&output.stdout)
+                );
+            }
+        },
+    );
+}
+
+#[test]
+fn it_inserts_a_path_after_an_anchor_in_fish_config() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .config/fish/config.fish file in the virtual home directory
+    let fish_config = home.child(".config/fish/config.fish");
+    create_dir_all(fish_config.parent().unwrap()).unwrap();
+    fish_config.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/fish".to_string())),
+        ],
+        || {
+            let position = Position::after("/usr/bin", Fallback::Back);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                add_to_path("/test", &position, None, &UpdateOptions::new())
+            );
+            assert!(
+                predicate::str::contains(
+                    "if contains -- \"/usr/bin\" $PATH; set -l spliced; for entry in $PATH; \
+                     set -a spliced $entry; test \"$entry\" = \"/usr/bin\"; \
+                     and set -a spliced \"/test\"; end; set -gx PATH $spliced; \
+                     else; set -gx PATH $PATH \"/test\"; end"
+                )
+                .from_utf8()
+                .from_file_path()
+                .eval(fish_config.path())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_does_not_insert_a_path_before_an_anchor_twice_in_zshrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .zshrc file in the virtual home directory
+    let zshrc = home.child(".zshrc");
+    zshrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            let position = Position::before("/usr/bin", Fallback::Front);
+            let options = UpdateOptions::new().move_existing(true);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                add_to_path("/test", &position, None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                add_to_path("/test", &position, None, &options)
+            );
+
+            // The anchor itself is not considered to be added by the line
+            assert_eq!(Ok(UpdateType::Success), prepend_to_path("/usr/bin", None));
+        },
+    );
+}
+
+// --- Replacement ---
+
+#[test]
+fn it_replaces_a_path_in_place_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory with an existing export command
+    let bashrc = home.child(".bashrc");
+    bashrc
+        .write_str("# Old tool\nexport PATH=\"/old/bin:$PATH\"\nexport PATH=\"$PATH:/other/bin\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Replaced),
+                replace_in_path("/old/bin", "/new/bin", &UpdateOptions::new())
+            );
+            assert_eq!(
+                "# Old tool\nexport PATH=\"/new/bin:$PATH\"\nexport PATH=\"$PATH:/other/bin\"\n",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_replaces_a_path_in_a_guarded_command_in_fish_config() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .config/fish/config.fish file with an existing guarded set command
+    let fish_config = home.child(".config/fish/config.fish");
+    create_dir_all(fish_config.parent().unwrap()).unwrap();
+    fish_config
+        .write_str(
+            "test -d \"$HOME/.oldtool/bin\"; and set -gx PATH $PATH \"$HOME/.oldtool/bin\"\n",
+        )
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/fish".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Replaced),
+                replace_in_path(
+                    "~/.oldtool/bin",
+                    "~/.local/share/newtool/bin",
+                    &UpdateOptions::new()
+                )
+            );
+            assert_eq!(
+                "test -d \"$HOME/.local/share/newtool/bin\"; \
+                 and set -gx PATH $PATH \"$HOME/.local/share/newtool/bin\"\n",
+                std::fs::read_to_string(fish_config.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_removes_the_old_path_if_the_new_path_is_already_present_in_zshrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .zshrc file in the virtual home directory with both paths
+    let zshrc = home.child(".zshrc");
+    zshrc
+        .write_str("# Old\nexport PATH=\"/old/bin:$PATH\"\n# New\nexport PATH=\"/new/bin:$PATH\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Replaced),
+                replace_in_path("/old/bin", "/new/bin", &UpdateOptions::new())
+            );
+            // The comment of the old path is left to the user, as the comment
+            // it was added with is unknown
+            assert_eq!(
+                "# Old\n# New\nexport PATH=\"/new/bin:$PATH\"\n",
+                std::fs::read_to_string(zshrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_prepends_the_new_path_if_the_old_path_is_not_present_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Success),
+                replace_in_path("/old/bin", "/new/bin", &UpdateOptions::new())
+            );
+            assert!(
+                predicate::str::contains("export PATH=\"/new/bin:$PATH\"")
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(bashrc.path())
+            );
+        },
+    );
+}
+
+// --- Batch ---
+
+#[test]
+fn it_prepends_several_paths_with_a_single_line_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory with one of the paths
+    let bashrc = home.child(".bashrc");
+    bashrc.write_str("export PATH=\"/b:$PATH\"\n").unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(vec![
+                    (PathBuf::from("/a"), UpdateType::Success),
+                    (PathBuf::from("/b"), UpdateType::AlreadyInPath),
+                    (PathBuf::from("/c"), UpdateType::Success),
+                ]),
+                add_all_to_path(
+                    &["/a", "/b", "/c"],
+                    &Position::Front,
+                    Some("Toolchain"),
+                    &UpdateOptions::new()
+                )
+            );
+            assert_eq!(
+                "export PATH=\"/b:$PATH\"\n\n# Toolchain\nexport PATH=\"/a:/c:$PATH\"",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_appends_several_paths_with_a_single_line_in_fish_config() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create config.fish file in the virtual home directory
+    let config_fish = home.child(".config/fish/config.fish");
+    config_fish.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/usr/bin/fish".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(vec![
+                    (PathBuf::from("/a"), UpdateType::Success),
+                    (PathBuf::from("/b"), UpdateType::Success),
+                ]),
+                add_all_to_path(
+                    &["/a", "/b"],
+                    &Position::Back,
+                    Some("Toolchain"),
+                    &UpdateOptions::new()
+                )
+            );
+            assert!(
+                predicate::str::contains("# Toolchain\nset -gx PATH $PATH \"/a\" \"/b\"")
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(config_fish.path())
+            );
+
+            // Adding the paths again leaves the configuration file untouched
+            assert_eq!(
+                Ok(vec![
+                    (PathBuf::from("/a"), UpdateType::AlreadyInPath),
+                    (PathBuf::from("/b"), UpdateType::AlreadyInPath),
+                ]),
+                add_all_to_path(
+                    &["/a", "/b"],
+                    &Position::Back,
+                    Some("Toolchain"),
+                    &UpdateOptions::new()
+                )
+            );
+            assert_eq!(
+                1,
+                std::fs::read_to_string(config_fish.path())
+                    .unwrap()
+                    .matches("# Toolchain")
+                    .count()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_adds_a_path_given_twice_only_once_in_zshrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .zshrc file in the virtual home directory
+    let zshrc = home.child(".zshrc");
+    zshrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(vec![
+                    (PathBuf::from("/a"), UpdateType::Success),
+                    (PathBuf::from("/a/"), UpdateType::AlreadyInPath),
+                ]),
+                add_all_to_path(
+                    &["/a", "/a/"],
+                    &Position::Front,
+                    None,
+                    &UpdateOptions::new()
+                )
+            );
+            assert!(
+                predicate::str::contains("export PATH=\"/a:$PATH\"")
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(zshrc.path())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_guards_each_path_separately_if_only_if_exists_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().only_if_exists(true);
+            assert!(
+                add_all_to_path(&["/a", "/b"], &Position::Front, Some("Toolchain"), &options)
+                    .is_ok()
+            );
+            assert_eq!(
+                "\n# Toolchain\n[ -d \"/b\" ] && export PATH=\"/b:$PATH\"\n[ -d \"/a\" ] && export PATH=\"/a:$PATH\"",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+// --- Query ---
+
+#[test]
+fn it_finds_a_path_configured_in_several_config_files() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc and .zshrc files in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc
+        .write_str("# Test\nexport PATH=\"/test:$PATH\"\n")
+        .unwrap();
+    let zshrc = home.child(".zshrc");
+    zshrc
+        .write_str("alias ll='ls -l'\n\nexport PATH=\"$PATH:/test/\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("PATH", Some("/usr/bin:/bin".to_string())),
+        ],
+        || {
+            let status = find_in_path("/test", &UpdateOptions::new()).unwrap();
+
+            assert!(!status.in_process);
+            assert!(status.requires_restart());
+            assert_eq!(
+                vec![
+                    Location::ConfigFile {
+                        path: bashrc.path().to_path_buf(),
+                        line: 2
+                    },
+                    Location::ConfigFile {
+                        path: zshrc.path().to_path_buf(),
+                        line: 3
+                    },
+                ],
+                status.locations
+            );
+
+            // The configuration files are left untouched
+            assert_eq!(
+                "# Test\nexport PATH=\"/test:$PATH\"\n",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_finds_a_path_in_the_process_path_only() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("PATH", Some("/usr/bin:/test:/bin".to_string())),
+        ],
+        || {
+            let status = find_in_path("/test", &UpdateOptions::new()).unwrap();
+
+            assert!(status.in_process);
+            assert!(!status.is_configured());
+            assert!(!status.requires_restart());
+        },
+    );
+}
+
+// --- Activation ---
+
+#[test]
+fn it_returns_the_command_sourcing_zshrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .zshrc file in the virtual home directory
+    let zshrc = home.child(".zshrc");
+    zshrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            let activations = activation_commands(&UpdateOptions::new()).unwrap();
+
+            assert_eq!(
+                vec![Activation {
+                    shell: "zsh".to_string(),
+                    command: "source ~/.zshrc".to_string(),
+                }],
+                activations
+            );
+            assert_eq!(
+                "To update the PATH of the current zsh session, run `source ~/.zshrc` or restart your shell.",
+                activations[0].message()
+            );
+            assert_eq!(
+                "Run: source ~/.zshrc",
+                activations[0].format("Run: {command}")
+            );
+        },
+    );
+}
+
+#[test]
+fn it_returns_the_command_sourcing_fish_config() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create config.fish file in the virtual home directory
+    let config_fish = home.child(".config/fish/config.fish");
+    config_fish.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/usr/bin/fish".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(vec![Activation {
+                    shell: "fish".to_string(),
+                    command: "source ~/.config/fish/config.fish".to_string(),
+                }]),
+                activation_commands(&UpdateOptions::new())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_uses_a_tilde_for_a_home_directory_with_spaces_in_the_activation_command() {
+    // Create the virtual home directory, with a space in its name
+    let root = assert_fs::TempDir::new().unwrap();
+    let home = root.child("my home");
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                "source ~/.bashrc",
+                activation_commands(&UpdateOptions::new()).unwrap()[0].command
+            );
+        },
+    );
+}
+
+#[test]
+fn it_returns_the_activation_command_for_the_home_and_shell_of_the_options() {
+    // Create the home directory of the session, and the home directory of
+    // another user
+    let home = assert_fs::TempDir::new().unwrap();
+    let other_home = assert_fs::TempDir::new().unwrap();
+    other_home.child(".zshrc").touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().home(other_home.path()).shell("zsh");
+
+            assert_eq!(
+                Ok(vec![Activation {
+                    shell: "zsh".to_string(),
+                    command: format!("source {}/.zshrc", other_home.path().display()),
+                }]),
+                activation_commands(&options)
+            );
+        },
+    );
+}
+
+#[test]
+fn it_returns_the_activation_commands_of_the_system_wide_targets() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("XDG_CONFIG_HOME", None),
+        ],
+        || {
+            let command = |options: &UpdateOptions| {
+                activation_commands(options)
+                    .unwrap()
+                    .into_iter()
+                    .map(|activation| activation.command)
+                    .collect::<Vec<_>>()
+            };
+            let options = UpdateOptions::new();
+
+            assert_eq!(
+                vec!["source /etc/profile.d/tool.sh"],
+                command(&options.clone().target(Target::profile_d("tool")))
+            );
+            assert_eq!(
+                vec!["source /usr/share/fish/vendor_conf.d/tool.fish"],
+                command(
+                    &options
+                        .clone()
+                        .target(Target::profile_d("tool"))
+                        .shell("fish")
+                )
+            );
+            assert_eq!(
+                vec!["set -a; . ~/.config/environment.d/50-tool.conf; set +a"],
+                command(&options.clone().target(Target::environment_d("50-tool")))
+            );
+            assert_eq!(
+                vec!["set -a; . /etc/environment; set +a"],
+                command(&options.clone().target(Target::Environment))
+            );
+            assert_eq!(
+                vec!["eval \"$(/usr/libexec/path_helper -s)\""],
+                command(&options.clone().target(Target::paths_d("tool")))
+            );
+
+            // Fish cannot load the assignments of environment files
+            assert!(command(&options.clone().target(Target::Environment).shell("fish")).is_empty());
+
+            // Files of new users or of another system aren't loaded
+            assert!(command(&options.clone().target(Target::skeleton("/etc/skel"))).is_empty());
+            assert!(command(&options.clone().root(home.path())).is_empty());
+        },
+    );
+}
+
+// --- Process ---
+
+#[test]
+fn it_updates_the_path_of_the_current_process_when_prepending_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("PATH", Some("/usr/bin:/bin".to_string())),
+        ],
+        || {
+            // SAFETY: the environment is only accessed while holding the lock
+            // of `temp_env`
+            let options = unsafe { UpdateOptions::new().update_process(true) };
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/test", None, &options)
+            );
+            assert_eq!("/test:/usr/bin:/bin", std::env::var("PATH").unwrap());
+
+            // The path is not added twice to the PATH of the process
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options("/test/", None, &options)
+            );
+            assert_eq!("/test:/usr/bin:/bin", std::env::var("PATH").unwrap());
+        },
+    );
+}
+
+#[test]
+fn it_does_not_update_the_path_of_the_current_process_by_default() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("PATH", Some("/usr/bin:/bin".to_string())),
+        ],
+        || {
+            assert_eq!(Ok(UpdateType::Success), append_to_path("/test", None));
+            assert_eq!("/usr/bin:/bin", std::env::var("PATH").unwrap());
+        },
+    );
+}
+
+#[test]
+fn it_adds_a_path_to_the_path_of_a_child_process() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("PATH", Some("/usr/bin:/bin".to_string())),
+        ],
+        || {
+            let mut command = Command::new("/bin/sh");
+            command.args(["-c", "echo $PATH"]);
+
+            add_to_command_path(
+                &mut command,
+                &["/test", "/bin"],
+                &Position::Back,
+                &UpdateOptions::new(),
+            )
+            .unwrap();
+
+            let output = command.output().unwrap();
+            assert_eq!(
+                "/usr/bin:/bin:/test\n",
+                String::from_utf8(output.stdout).unwrap()
+            );
+
+            // The PATH of the current process is left untouched
+            assert_eq!("/usr/bin:/bin", std::env::var("PATH").unwrap());
+        },
+    );
+}
+
+#[test]
+fn it_adds_a_path_to_the_path_set_on_a_command() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    temp_env::with_vars(
+        [("HOME", Some(home.path().to_string_lossy().to_string()))],
+        || {
+            let mut command = Command::new("/bin/sh");
+            command.args(["-c", "echo $PATH"]).env("PATH", "/opt/bin");
+
+            add_to_command_path(
+                &mut command,
+                &["/test"],
+                &Position::Front,
+                &UpdateOptions::new(),
+            )
+            .unwrap();
+
+            let output = command.output().unwrap();
+            assert_eq!(
+                "/test:/opt/bin\n",
+                String::from_utf8(output.stdout).unwrap()
+            );
+        },
+    );
+}
+
+// --- Other variables ---
+
+#[test]
+fn it_prepends_a_path_to_ld_library_path_without_an_empty_entry_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().variable("LD_LIBRARY_PATH");
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/sdk/lib", None, &options)
+            );
+            assert!(
+                predicate::str::contains(
+                    "export LD_LIBRARY_PATH=\"/sdk/lib${LD_LIBRARY_PATH:+:$LD_LIBRARY_PATH}\""
+                )
+                .from_utf8()
+                .from_file_path()
+                .eval(bashrc.path())
+            );
+
+            // The path is detected in the line on the next call
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options("/sdk/lib", None, &options)
+            );
+
+            // The line doesn't leave an empty entry when the variable is unset
+            let script = format!(". {}; echo \"$LD_LIBRARY_PATH\"", bashrc.path().display());
+            let output = Command::new("/bin/sh")
+                .args(["-c", &script])
+                .env_remove("LD_LIBRARY_PATH")
+                .output()
+                .unwrap();
+            assert_eq!("/sdk/lib\n", String::from_utf8_lossy(&output.stdout));
+
+            let output = Command::new("/bin/sh")
+                .args(["-c", &script])
+                .env("LD_LIBRARY_PATH", "/usr/local/lib")
+                .output()
+                .unwrap();
+            assert_eq!(
+                "/sdk/lib:/usr/local/lib\n",
+                String::from_utf8_lossy(&output.stdout)
+            );
+        },
+    );
+}
+
+#[test]
+fn it_appends_a_path_to_manpath_keeping_the_default_search_path_in_zshrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .zshrc file in the virtual home directory
+    let zshrc = home.child(".zshrc");
+    zshrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().variable("MANPATH");
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                append_to_path_with_options("/sdk/man", None, &options)
+            );
+            assert!(
+                predicate::str::contains("export MANPATH=\"$MANPATH:/sdk/man\"")
+                    .from_utf8()
+                    .from_file_path()
+                    .eval(zshrc.path())
+            );
+
+            // The leading separator keeps the default search path when the
+            // variable is unset
+            let output = Command::new("/bin/sh")
+                .args([
+                    "-c",
+                    &format!(". {}; echo \"$MANPATH\"", zshrc.path().display()),
+                ])
+                .env_remove("MANPATH")
+                .output()
+                .unwrap();
+            assert_eq!(":/sdk/man\n", String::from_utf8_lossy(&output.stdout));
+        },
+    );
+}
+
+#[test]
+fn it_prepends_a_path_to_manpath_in_fish_config() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create config.fish file in the virtual home directory
+    let config_fish = home.child(".config/fish/config.fish");
+    config_fish.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/usr/bin/fish".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().variable("MANPATH");
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/sdk/man", None, &options)
+            );
+            assert!(
+                predicate::str::contains(
+                    "set -q MANPATH; or set -gx MANPATH \"\"; set -gx MANPATH \"/sdk/man\" $MANPATH"
+                )
+                .from_utf8()
+                .from_file_path()
+                .eval(config_fish.path())
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options("/sdk/man", None, &options)
+            );
+        },
+    );
+}
+
+#[test]
+fn it_appends_a_path_to_xdg_data_dirs_as_a_path_variable_in_fish_config() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create config.fish file in the virtual home directory
+    let config_fish = home.child(".config/fish/config.fish");
+    config_fish.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/usr/bin/fish".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().variable("XDG_DATA_DIRS");
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                append_to_path_with_options("/sdk/share", None, &options)
+            );
+            assert!(
+                predicate::str::contains(
+                    "set -q XDG_DATA_DIRS[1]; or set -gx --path XDG_DATA_DIRS \"/usr/local/share\" \"/usr/share\"; set -gx --path XDG_DATA_DIRS $XDG_DATA_DIRS \"/sdk/share\""
+                )
+                .from_utf8()
+                .from_file_path()
+                .eval(config_fish.path())
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                append_to_path_with_options("/sdk/share", None, &options)
+            );
+        },
+    );
+}
+
+#[test]
+fn it_keeps_the_default_value_of_the_xdg_variables_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let data_options = UpdateOptions::new().variable("XDG_DATA_DIRS");
+            let config_options = UpdateOptions::new().variable("XDG_CONFIG_DIRS");
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/share", None, &data_options)
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options("/opt/tool/share", None, &data_options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Success),
+                append_to_path_with_options("/opt/tool/xdg", None, &config_options)
+            );
+            assert_eq!(
+                "\nexport XDG_DATA_DIRS=\"/opt/tool/share:${XDG_DATA_DIRS:-/usr/local/share:/usr/share}\"\nexport XDG_CONFIG_DIRS=\"${XDG_CONFIG_DIRS:-/etc/xdg}:/opt/tool/xdg\"",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+
+            // The default directories are kept when the variables are unset
+            let output = Command::new("/bin/sh")
+                .args([
+                    "-c",
+                    &format!(
+                        ". {}; echo \"$XDG_DATA_DIRS\"; echo \"$XDG_CONFIG_DIRS\"",
+                        bashrc.path().display()
+                    ),
+                ])
+                .env_remove("XDG_DATA_DIRS")
+                .env_remove("XDG_CONFIG_DIRS")
+                .output()
+                .unwrap();
+            assert_eq!(
+                "/opt/tool/share:/usr/local/share:/usr/share\n/etc/xdg:/opt/tool/xdg\n",
+                String::from_utf8_lossy(&output.stdout)
+            );
+
+            // The current directories are kept when the variables are set
+            let output = Command::new("/bin/sh")
+                .args([
+                    "-c",
+                    &format!(
+                        ". {}; echo \"$XDG_DATA_DIRS\"; echo \"$XDG_CONFIG_DIRS\"",
+                        bashrc.path().display()
+                    ),
+                ])
+                .env("XDG_DATA_DIRS", "/usr/share")
+                .env("XDG_CONFIG_DIRS", "/etc/custom")
+                .output()
+                .unwrap();
+            assert_eq!(
+                "/opt/tool/share:/usr/share\n/etc/custom:/opt/tool/xdg\n",
+                String::from_utf8_lossy(&output.stdout)
+            );
+
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/opt/tool/share", None, &data_options)
+            );
+            assert_eq!(
+                "\nexport XDG_CONFIG_DIRS=\"${XDG_CONFIG_DIRS:-/etc/xdg}:/opt/tool/xdg\"",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_does_not_confuse_entries_of_different_variables_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc
+        .write_str("export PKG_CONFIG_PATH=\"/sdk${PKG_CONFIG_PATH:+:$PKG_CONFIG_PATH}\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(Ok(UpdateType::Success), prepend_to_path("/sdk", None));
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options(
+                    "/sdk",
+                    None,
+                    &UpdateOptions::new().variable("PKG_CONFIG_PATH")
+                )
+            );
+        },
+    );
+}
+
+#[test]
+fn it_keeps_the_default_search_path_when_adding_to_the_manpath_of_a_child_process() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("MANPATH", None),
+        ],
+        || {
+            let mut command = Command::new("/bin/sh");
+            command.args(["-c", "echo $MANPATH"]);
+
+            add_to_command_path(
+                &mut command,
+                &["/sdk/man"],
+                &Position::Front,
+                &UpdateOptions::new().variable("MANPATH"),
+            )
+            .unwrap();
+
+            let output = command.output().unwrap();
+            assert_eq!("/sdk/man:\n", String::from_utf8(output.stdout).unwrap());
+        },
+    );
+}
+
+// --- Removal ---
+
+#[test]
+fn it_removes_a_path_and_its_comment_from_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc
+        .write_str("alias ll='ls -l'\n\n# Tool\nexport PATH=\"/tool/bin:$PATH\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/tool/bin/", Some("Tool"), &UpdateOptions::new())
+            );
+            assert_eq!(
+                "alias ll='ls -l'\n",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+
+            assert_eq!(
+                Ok(UpdateType::NotInPath),
+                remove_from_path("/tool/bin", Some("Tool"), &UpdateOptions::new())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_removes_a_path_from_a_compound_command_in_bashrc_without_breaking_it() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory, adding a path in an
+    // if block as the default .profile of Ubuntu does
+    let bashrc = home.child(".bashrc");
+    bashrc
+        .write_str(
+            "# set PATH so it includes user's private bin if it exists\nif [ -d \"$HOME/bin\" ] ; then\n    PATH=\"$HOME/bin:$PATH\"\nfi\n",
+        )
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            // Lines written by pathman are removed, whatever their position
+            assert_eq!(
+                Ok(UpdateType::Success),
+                add_to_path(
+                    "/opt/tool/bin",
+                    &Position::after("/usr/bin", Fallback::Back),
+                    Some("Tool"),
+                    &UpdateOptions::new()
+                )
+            );
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/opt/tool/bin", Some("Tool"), &UpdateOptions::new())
+            );
+
+            // Other lines only lose the entry
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("~/bin", None, &UpdateOptions::new())
+            );
+            assert_eq!(
+                "# set PATH so it includes user's private bin if it exists\nif [ -d \"$HOME/bin\" ] ; then\n    PATH=\"$PATH\"\nfi\n",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+
+            let status = Command::new("bash")
+                .args(["-n", &bashrc.path().to_string_lossy()])
+                .status()
+                .unwrap();
+            assert!(status.success());
+        },
+    );
+}
+
+#[test]
+fn it_does_not_empty_the_path_when_removing_a_path_from_a_compound_command_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory, setting the PATH
+    // and a variable in if blocks
+    let bashrc = home.child(".bashrc");
+    let content = "if [ -d /opt/tool/bin ]; then\n    export PATH=/opt/tool/bin\nfi\nif [ -d /opt/java ]; then\n    export JAVA_HOME=\"/opt/java\"\nfi\n";
+    bashrc.write_str(content).unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let error = Err(UnableToRemoveEntry(format!(
+                "{}:2",
+                bashrc.path().display()
+            )));
+            assert_eq!(
+                error,
+                remove_from_path("/opt/tool/bin", None, &UpdateOptions::new())
+            );
+
+            let error = Err(UnableToRemoveEntry(format!(
+                "{}:5",
+                bashrc.path().display()
+            )));
+            assert_eq!(
+                error,
+                unset_env_var("JAVA_HOME", None, &UpdateOptions::new())
+            );
+            assert_eq!(content, std::fs::read_to_string(bashrc.path()).unwrap());
+        },
+    );
+}
+
+#[test]
+fn it_keeps_a_comment_not_written_for_the_removed_path_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory, with a comment of
+    // the user describing the following lines
+    let bashrc = home.child(".bashrc");
+    bashrc
+        .write_str(
+            "# Local binaries\nexport PATH=\"/tool/bin:$PATH\"\nexport PATH=\"$HOME/bin:$PATH\"\n",
+        )
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/tool/bin", Some("Tool"), &UpdateOptions::new())
+            );
+            assert_eq!(
+                "# Local binaries\nexport PATH=\"$HOME/bin:$PATH\"\n",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_removes_a_path_from_a_line_adding_other_paths_in_zshrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .zshrc file in the virtual home directory
+    let zshrc = home.child(".zshrc");
+    zshrc
+        .write_str("# Toolchain\nexport PATH=\"/a:/b:$PATH\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/a", None, &UpdateOptions::new())
+            );
+            assert_eq!(
+                "# Toolchain\nexport PATH=\"/b:$PATH\"\n",
+                std::fs::read_to_string(zshrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_removes_a_path_from_a_line_adding_other_paths_in_fish_config() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create config.fish file in the virtual home directory
+    let config_fish = home.child(".config/fish/config.fish");
+    config_fish
+        .write_str("set -gx PATH $PATH \"/a\" \"/b\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/usr/bin/fish".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/b", None, &UpdateOptions::new())
+            );
+            assert_eq!(
+                "set -gx PATH $PATH \"/a\"\n",
+                std::fs::read_to_string(config_fish.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_removes_a_path_from_ld_library_path_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc
+        .write_str("export LD_LIBRARY_PATH=\"/a:/b${LD_LIBRARY_PATH:+:$LD_LIBRARY_PATH}\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path(
+                    "/b",
+                    None,
+                    &UpdateOptions::new().variable("LD_LIBRARY_PATH")
+                )
+            );
+            assert_eq!(
+                "export LD_LIBRARY_PATH=\"/a${LD_LIBRARY_PATH:+:$LD_LIBRARY_PATH}\"\n",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+// --- Environment variables ---
+
+#[test]
+fn it_sets_an_environment_variable_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Success),
+                set_env_var(
+                    "JAVA_HOME",
+                    "/opt/java",
+                    Some("Java"),
+                    &UpdateOptions::new()
+                )
+            );
+            assert_eq!(
+                "\n# Java\nexport JAVA_HOME=\"/opt/java\"",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+
+            // Setting the same value again leaves the file untouched
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                set_env_var(
+                    "JAVA_HOME",
+                    "/opt/java/",
+                    Some("Java"),
+                    &UpdateOptions::new()
+                )
+            );
+            assert_eq!(
+                "\n# Java\nexport JAVA_HOME=\"/opt/java\"",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_updates_an_environment_variable_in_place_in_zshrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .zshrc file in the virtual home directory with the variable set
+    let zshrc = home.child(".zshrc");
+    zshrc
+        .write_str("# Java\nexport JAVA_HOME=/opt/java-17\nalias ll='ls -l'\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Replaced),
+                set_env_var("JAVA_HOME", "/opt/java-21", None, &UpdateOptions::new())
+            );
+            assert_eq!(
+                "# Java\nexport JAVA_HOME=/opt/java-21\nalias ll='ls -l'\n",
+                std::fs::read_to_string(zshrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_sets_an_environment_variable_in_fish_config() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create config.fish file in the virtual home directory
+    let config = home.child(".config/fish/config.fish");
+    config.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/usr/bin/fish".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Success),
+                set_env_var("EDITOR", "vim", None, &UpdateOptions::new())
+            );
+            assert_eq!(
+                Ok(UpdateType::Replaced),
+                set_env_var("EDITOR", "nvim", None, &UpdateOptions::new())
+            );
+            assert_eq!(
+                "\nset -gx EDITOR \"nvim\"",
+                std::fs::read_to_string(config.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_unsets_an_environment_variable_and_its_comment_from_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory with the variable set
+    let bashrc = home.child(".bashrc");
+    bashrc
+        .write_str("alias ll='ls -l'\n\n# Java\nexport JAVA_HOME=\"/opt/java\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                unset_env_var("JAVA_HOME", Some("Java"), &UpdateOptions::new())
+            );
+            assert_eq!(
+                "alias ll='ls -l'\n",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+
+            assert_eq!(
+                Ok(UpdateType::NotInPath),
+                unset_env_var("JAVA_HOME", None, &UpdateOptions::new())
+            );
+        },
+    );
+}
+
+#[test]
+fn it_sets_a_literal_environment_variable_in_bashrc() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().literal(true);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                set_env_var("PS_FORMAT", "a/./b", None, &options)
+            );
+
+            // Values that are equivalent as paths are still different values
+            assert_eq!(
+                Ok(UpdateType::Replaced),
+                set_env_var("PS_FORMAT", "a/b", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                set_env_var("PS_FORMAT", "a/b", None, &options)
+            );
+
+            // Variable references are neither expanded nor left to the shell
+            assert_eq!(
+                Ok(UpdateType::Replaced),
+                set_env_var("PS_FORMAT", "$UNDEFINED `id` ~", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                set_env_var("PS_FORMAT", "$UNDEFINED `id` ~", None, &options)
+            );
+            assert_eq!(
+                "\nexport PS_FORMAT=\"\\$UNDEFINED \\`id\\` ~\"",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+
+            let script = format!(". {}; echo \"$PS_FORMAT\"", bashrc.path().display());
+            let output = Command::new("/bin/sh")
+                .args(["-c", &script])
+                .output()
+                .unwrap();
+            assert_eq!(
+                "$UNDEFINED `id` ~\n",
+                String::from_utf8_lossy(&output.stdout)
+            );
+        },
+    );
+}
+
+#[test]
+fn it_sets_a_literal_environment_variable_in_the_current_process() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("PS_FORMAT", None),
+        ],
+        || {
+            // SAFETY: the environment is only accessed while holding the lock
+            // of `temp_env`
+            let options = unsafe { UpdateOptions::new().literal(true).update_process(true) };
+
+            // The process gets the same value as the configuration file
+            assert_eq!(
+                Ok(UpdateType::Success),
+                set_env_var("PS_FORMAT", "$HOME/x", None, &options)
+            );
+            assert_eq!("$HOME/x", std::env::var("PS_FORMAT").unwrap());
+
+            assert_eq!(
+                Ok(UpdateType::Replaced),
+                set_env_var("PS_FORMAT", "$PATHMAN_UNDEFINED ok", None, &options)
+            );
+            assert_eq!("$PATHMAN_UNDEFINED ok", std::env::var("PS_FORMAT").unwrap());
+            assert_eq!(
+                "\nexport PS_FORMAT=\"\\$PATHMAN_UNDEFINED ok\"",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_does_not_set_a_variable_that_the_current_process_cannot_get() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("TOOL_HOME", None),
+        ],
+        || {
+            // SAFETY: the environment is only accessed while holding the lock
+            // of `temp_env`
+            let options = unsafe {
+                UpdateOptions::new()
+                    .expansion(Expansion::Deferred)
+                    .update_process(true)
+            };
+
+            // The value can't be expanded for the process, so nothing is
+            // written
+            assert_eq!(
+                Err(UndefinedVariable("PATHMAN_UNDEFINED".to_string())),
+                set_env_var("TOOL_HOME", "$PATHMAN_UNDEFINED/tool", None, &options)
+            );
+            assert_eq!("", std::fs::read_to_string(bashrc.path()).unwrap());
+            assert!(std::env::var("TOOL_HOME").is_err());
+        },
+    );
+}
+
+#[test]
+fn it_sets_a_literal_environment_variable_in_etc_environment() {
+    // Create the root directory with an existing /etc/environment file
+    let root = assert_fs::TempDir::new().unwrap();
+    let environment = root.child("etc/environment");
+    environment.write_str("GREETING=\"a/./b\"\n").unwrap();
+
+    let options = UpdateOptions::new()
+        .target(Target::Environment)
+        .root(root.path())
+        .literal(true);
+
+    assert_eq!(
+        Ok(UpdateType::Replaced),
+        set_env_var("GREETING", "a/b", None, &options)
+    );
+    assert_eq!(
+        Ok(UpdateType::Replaced),
+        set_env_var("GREETING", "$HOME", None, &options)
+    );
+    assert_eq!(
+        Ok(UpdateType::AlreadyInPath),
+        set_env_var("GREETING", "$HOME", None, &options)
+    );
+    assert_eq!(
+        "GREETING=\"$HOME\"\n",
+        std::fs::read_to_string(environment.path()).unwrap()
+    );
+}
+
+#[test]
+fn it_rejects_invalid_variable_names() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+
+    // Create .bashrc file in the virtual home directory
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            for name in ["", "1PATH", "JAVA-HOME", "X=1; rm -rf ~; Y"] {
+                assert_eq!(
+                    Err(InvalidVariableName(name.to_string())),
+                    set_env_var(name, "/opt/java", None, &UpdateOptions::new())
+                );
+                assert_eq!(
+                    Err(InvalidVariableName(name.to_string())),
+                    unset_env_var(name, None, &UpdateOptions::new())
+                );
+                assert_eq!(
+                    Err(InvalidVariableName(name.to_string())),
+                    append_to_path_with_options(
+                        "/opt/bin",
+                        None,
+                        &UpdateOptions::new().variable(name)
+                    )
+                );
+            }
+            assert_eq!("", std::fs::read_to_string(bashrc.path()).unwrap());
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                set_env_var("_JAVA_HOME2", "/opt/java", None, &UpdateOptions::new())
+            );
+        },
+    );
+}
+
+// --- systemd environment.d ---
+
+#[test]
+fn it_prepends_a_path_to_an_environment_d_file() {
+    // Create the virtual home directory, without any environment.d file
+    let home = assert_fs::TempDir::new().unwrap();
+    let file = home.child(".config/environment.d/50-tool.conf");
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("XDG_CONFIG_HOME", None),
+        ],
+        || {
+            let options = UpdateOptions::new().target(Target::environment_d("50-tool"));
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/bin", Some("Tool"), &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options("/opt/tool/bin/", Some("Tool"), &options)
+            );
+            assert_eq!(
+                "# Tool\nPATH=/opt/tool/bin:${PATH}",
+                std::fs::read_to_string(file.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_writes_the_environment_d_file_to_the_xdg_config_home() {
+    // Create the virtual home directory and a separate configuration directory
+    let home = assert_fs::TempDir::new().unwrap();
+    let config_home = assert_fs::TempDir::new().unwrap();
+    let file = config_home.child("environment.d/50-tool.conf");
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+            (
+                "XDG_CONFIG_HOME",
+                Some(config_home.path().to_string_lossy().to_string()),
+            ),
+        ],
+        || {
+            let options = UpdateOptions::new().target(Target::environment_d("50-tool"));
+            let path = home.path().join("my tools/bin");
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                append_to_path_with_options(&path, None, &options)
+            );
+            assert_eq!(
+                "PATH=${PATH}:$HOME/my\\ tools/bin",
+                std::fs::read_to_string(file.path()).unwrap()
+            );
+            assert!(!home.child(".config").exists());
+
+            // The path is found in the file and can be removed
+            assert_eq!(
+                vec![Location::ConfigFile {
+                    path: file.path().to_path_buf(),
+                    line: 1
+                }],
+                find_in_path(&path, &options).unwrap().locations
+            );
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path(&path, None, &options)
+            );
+            assert_eq!("", std::fs::read_to_string(file.path()).unwrap());
+        },
+    );
+}
+
+#[test]
+fn it_updates_other_variables_in_an_environment_d_file() {
+    // Create the virtual home directory with an existing environment.d file
+    let home = assert_fs::TempDir::new().unwrap();
+    let file = home.child(".config/environment.d/50-tool.conf");
+    file.write_str("EDITOR=vim\n").unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("XDG_CONFIG_HOME", None),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::environment_d("50-tool"))
+                .variable("XDG_DATA_DIRS");
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/share", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options("/opt/tool/share", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Replaced),
+                set_env_var("EDITOR", "nvim", None, &options)
+            );
+            assert_eq!(
+                "EDITOR=nvim\n\nXDG_DATA_DIRS=/opt/tool/share:${XDG_DATA_DIRS:-/usr/local/share:/usr/share}",
+                std::fs::read_to_string(file.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_keeps_the_default_value_of_the_xdg_variables_in_an_environment_d_file() {
+    // Create the virtual home directory
+    let home = assert_fs::TempDir::new().unwrap();
+    let file = home.child(".config/environment.d/50-tool.conf");
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("XDG_CONFIG_HOME", None),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::environment_d("50-tool"))
+                .variable("XDG_CONFIG_DIRS");
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                append_to_path_with_options("/opt/tool/xdg", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                append_to_path_with_options("/opt/tool/xdg", None, &options)
+            );
+            assert_eq!(
+                "XDG_CONFIG_DIRS=${XDG_CONFIG_DIRS:-/etc/xdg}:/opt/tool/xdg",
+                std::fs::read_to_string(file.path()).unwrap()
+            );
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/opt/tool/xdg", None, &options)
+            );
+            assert_eq!("", std::fs::read_to_string(file.path()).unwrap());
+        },
+    );
+}
+
+// --- System-wide profile scripts ---
+
+#[test]
+fn it_prepends_a_path_to_the_system_wide_profile_scripts() {
+    // Create the virtual home directory and root directory
+    let home = assert_fs::TempDir::new().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let profile = root.child("etc/profile.d/tool.sh");
+    let fish = root.child("usr/share/fish/vendor_conf.d/tool.fish");
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::profile_d("tool"))
+                .root(root.path());
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/bin", Some("Tool"), &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options("/opt/tool/bin", Some("Tool"), &options)
+            );
+            assert_eq!(
+                "# Tool\nexport PATH=\"/opt/tool/bin:$PATH\"",
+                std::fs::read_to_string(profile.path()).unwrap()
+            );
+            assert_eq!(
+                "# Tool\nset -gx PATH \"/opt/tool/bin\" $PATH",
+                std::fs::read_to_string(fish.path()).unwrap()
+            );
+            assert!(!home.child(".zshrc").exists());
+        },
+    );
+}
+
+#[test]
+fn it_writes_paths_in_the_home_directory_relative_to_home_to_the_system_wide_profile_scripts() {
+    // Create the virtual home directory and root directory
+    let home = assert_fs::TempDir::new().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let profile = root.child("etc/profile.d/tool.sh");
+    let fish = root.child("usr/share/fish/vendor_conf.d/tool.fish");
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::profile_d("tool"))
+                .root(root.path());
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                append_to_path_with_options("~/tool/bin", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                append_to_path_with_options("$HOME/tool/bin", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Success),
+                append_to_path_with_options(home.path().join("bin"), None, &options)
+            );
+            assert_eq!(
+                format!(
+                    "export PATH=\"$PATH:$HOME/tool/bin\"\nexport PATH=\"$PATH:{}/bin\"",
+                    home.path().display()
+                ),
+                std::fs::read_to_string(profile.path()).unwrap()
+            );
+            assert_eq!(
+                format!(
+                    "set -gx PATH $PATH \"$HOME/tool/bin\"\nset -gx PATH $PATH \"{}/bin\"",
+                    home.path().display()
+                ),
+                std::fs::read_to_string(fish.path()).unwrap()
+            );
+
+            // Each user gets their own home directory in the PATH
+            let output = std::process::Command::new("/bin/sh")
+                .arg("-c")
+                .arg(format!(". {}; echo \"$PATH\"", profile.path().display()))
+                .env("HOME", "/home/alice")
+                .env("PATH", "/usr/bin")
+                .output()
+                .unwrap();
+            assert!(
+                String::from_utf8_lossy(&output.stdout)
+                    .starts_with("/usr/bin:/home/alice/tool/bin:")
+            );
+        },
+    );
+}
+
+#[test]
+fn it_removes_a_path_from_the_system_wide_profile_scripts() {
+    // Create the root directory with existing profile scripts
+    let home = assert_fs::TempDir::new().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let profile = root.child("etc/profile.d/tool.sh");
+    profile
+        .write_str("# Tool\nexport PATH=\"/opt/tool/bin:$PATH\"\n")
+        .unwrap();
+    let fish = root.child("usr/share/fish/vendor_conf.d/tool.fish");
+    fish.write_str("# Tool\nset -gx PATH \"/opt/tool/bin\" $PATH\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::profile_d("tool"))
+                .root(root.path());
+
+            assert_eq!(
+                vec![
+                    Location::ConfigFile {
+                        path: profile.path().to_path_buf(),
+                        line: 2
+                    },
+                    Location::ConfigFile {
+                        path: fish.path().to_path_buf(),
+                        line: 2
+                    }
+                ],
+                find_in_path("/opt/tool/bin", &options).unwrap().locations
+            );
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/opt/tool/bin", Some("Tool"), &options)
+            );
+            assert_eq!("", std::fs::read_to_string(profile.path()).unwrap());
+            assert_eq!("", std::fs::read_to_string(fish.path()).unwrap());
+            assert_eq!(
+                Ok(UpdateType::NotInPath),
+                remove_from_path("/opt/tool/bin", None, &options)
+            );
+        },
+    );
+}
+
+#[test]
+fn it_reports_insufficient_permissions_for_the_system_wide_profile_scripts() {
+    // Create the root directory with a read-only profile.d directory
+    let home = assert_fs::TempDir::new().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let profile_d = root.child("etc/profile.d");
+    profile_d.create_dir_all().unwrap();
+    set_permissions(&profile_d, Permissions::from_mode(0o555)).unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::profile_d("tool"))
+                .root(root.path());
+
+            assert_eq!(
+                Err(InsufficientPermissions(
+                    profile_d
+                        .path()
+                        .join("tool.sh")
+                        .to_string_lossy()
+                        .to_string()
+                )),
+                prepend_to_path_with_options("/opt/tool/bin", None, &options)
+            );
+        },
+    );
+}
+
+// --- /etc/environment ---
+
+#[test]
+fn it_splices_a_path_into_the_path_of_etc_environment() {
+    // Create the root directory with an existing /etc/environment file
+    let home = assert_fs::TempDir::new().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let environment = root.child("etc/environment");
+    environment
+        .write_str("LANG=en_US.UTF-8\nPATH=\"/usr/local/bin:/usr/bin:/bin\"\nEDITOR=vim\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::Environment)
+                .root(root.path());
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                add_to_path(
+                    "/opt/tool/bin",
+                    &Position::before("/usr/bin", Fallback::Front),
+                    Some("Tool"),
+                    &options
+                )
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options("/opt/tool/bin/", Some("Tool"), &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Success),
+                append_to_path_with_options("~/bin", None, &options.clone().home(home.path()))
+            );
+            assert_eq!(
+                format!(
+                    "LANG=en_US.UTF-8\nPATH=\"/usr/local/bin:/opt/tool/bin:/usr/bin:/bin:{}/bin\"\nEDITOR=vim\n",
+                    home.path().display()
+                ),
+                std::fs::read_to_string(environment.path()).unwrap()
+            );
+            assert_eq!(
+                vec![Location::ConfigFile {
+                    path: environment.path().to_path_buf(),
+                    line: 2
+                }],
+                find_in_path("/opt/tool/bin", &options).unwrap().locations
+            );
+        },
+    );
+}
+
+#[test]
+fn it_removes_and_replaces_a_path_in_etc_environment() {
+    // Create the root directory with an existing /etc/environment file
+    let home = assert_fs::TempDir::new().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let environment = root.child("etc/environment");
+    environment
+        .write_str("export PATH=/opt/old/bin:/opt/tool/bin:/usr/bin\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::Environment)
+                .root(root.path());
+
+            assert_eq!(
+                Ok(UpdateType::Replaced),
+                replace_in_path("/opt/old/bin", "/opt/new/bin", &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/opt/tool/bin", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::NotInPath),
+                remove_from_path("/opt/tool/bin", None, &options)
+            );
+            assert_eq!(
+                "export PATH=/opt/new/bin:/usr/bin\n",
+                std::fs::read_to_string(environment.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_adds_the_path_to_etc_environment_when_it_is_not_set() {
+    // Create the root directory without any /etc/environment file
+    let home = assert_fs::TempDir::new().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let environment = root.child("etc/environment");
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::Environment)
+                .root(root.path());
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/bin", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Success),
+                set_env_var("JAVA_HOME", "/opt/java", None, &options)
+            );
+            assert_eq!(
+                "PATH=\"/opt/tool/bin:/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin\"\nJAVA_HOME=\"/opt/java\"",
+                std::fs::read_to_string(environment.path()).unwrap()
+            );
+        },
+    );
+}
+
+// --- /etc/paths.d ---
+
+#[test]
+fn it_writes_a_path_to_a_paths_d_file() {
+    // Create the virtual home directory and root directory
+    let home = assert_fs::TempDir::new().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let file = root.child("etc/paths.d/tool");
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::paths_d("tool"))
+                .root(root.path());
+
+            assert_eq!(
+                vec![UpdateType::Success, UpdateType::Success],
+                add_all_to_path(
+                    &["/opt/tool/bin", "/opt/tool/sbin"],
+                    &Position::Front,
+                    Some("Tool"),
+                    &options
+                )
+                .unwrap()
+                .into_iter()
+                .map(|(_, update_type)| update_type)
+                .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                append_to_path_with_options("/opt/tool/bin/", None, &options)
+            );
+            assert_eq!(
+                "/opt/tool/bin\n/opt/tool/sbin\n",
+                std::fs::read_to_string(file.path()).unwrap()
+            );
+            assert_eq!(
+                vec![Location::ConfigFile {
+                    path: file.path().to_path_buf(),
+                    line: 2
+                }],
+                find_in_path("/opt/tool/sbin", &options).unwrap().locations
+            );
+        },
+    );
+}
+
+#[test]
+fn it_deletes_the_paths_d_file_when_its_last_path_is_removed() {
+    // Create the root directory with an existing manpaths.d file
+    let home = assert_fs::TempDir::new().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let file = root.child("etc/manpaths.d/tool");
+    file.write_str("/opt/old/share/man\n/opt/tool/share/man\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::paths_d("tool"))
+                .variable("MANPATH")
+                .root(root.path());
+
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/opt/old/share/man", None, &options)
+            );
+            assert_eq!(
+                "/opt/tool/share/man\n",
+                std::fs::read_to_string(file.path()).unwrap()
+            );
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/opt/tool/share/man", None, &options)
+            );
+            assert!(!file.exists());
+            assert_eq!(
+                Ok(UpdateType::NotInPath),
+                remove_from_path("/opt/tool/share/man", None, &options)
+            );
+        },
+    );
+}
+
+// --- Staged root ---
+
+#[test]
+fn it_updates_the_shell_configuration_file_under_a_staged_root() {
+    // Create the staging directory with the configuration file of the target
+    // user, and a separate home directory for the current user
+    let host_home = assert_fs::TempDir::new().unwrap();
+    let host_bashrc = host_home.child(".bashrc");
+    host_bashrc.touch().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let zshrc = root.child("home/builder/.zshrc");
+    zshrc.write_str("# Aliases\nalias ll='ls -l'\n").unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(host_home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .root(root.path())
+                .home("/home/builder")
+                .shell("zsh");
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("~/.tool/bin", Some("Tool"), &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options("/home/builder/.tool/bin", None, &options)
+            );
+            assert_eq!(
+                "# Aliases\nalias ll='ls -l'\n\n# Tool\nexport PATH=\"$HOME/.tool/bin:$PATH\"",
+                std::fs::read_to_string(zshrc.path()).unwrap()
+            );
+            assert_eq!("", std::fs::read_to_string(host_bashrc.path()).unwrap());
+        },
+    );
+}
+
+#[test]
+fn it_does_not_read_the_home_and_shell_of_the_current_process_under_a_staged_root() {
+    // Create the staging directory and a home directory for the current user
+    let host_home = assert_fs::TempDir::new().unwrap();
+    host_home.child(".bashrc").touch().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    root.child("home/builder/.bashrc").touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(host_home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Err(UnableToFindHomeDirectory),
+                prepend_to_path_with_options(
+                    "/opt/tool/bin",
+                    None,
+                    &UpdateOptions::new().root(root.path()).shell("bash")
+                )
+            );
+            assert_eq!(
+                Err(UnableToDetectShell),
+                prepend_to_path_with_options(
+                    "/opt/tool/bin",
+                    None,
+                    &UpdateOptions::new().root(root.path()).home("/home/builder")
+                )
+            );
+        },
+    );
+}
+
+#[test]
+fn it_updates_etc_environment_under_a_staged_root_without_a_home_directory() {
+    // Create the staging directory, without any home directory
+    let host_home = assert_fs::TempDir::new().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let environment = root.child("etc/environment");
+    environment
+        .write_str("PATH=\"/usr/local/bin:/usr/bin:/bin\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(host_home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::Environment)
+                .root(root.path());
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/bin", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/usr/local/bin", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Success),
+                set_env_var("JAVA_HOME", "/opt/java", None, &options)
+            );
+            assert_eq!(
+                Err(UnableToFindHomeDirectory),
+                append_to_path_with_options("~/bin", None, &options)
+            );
+            assert_eq!(
+                "PATH=\"/opt/tool/bin:/usr/bin:/bin\"\n\nJAVA_HOME=\"/opt/java\"",
+                std::fs::read_to_string(environment.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_updates_a_paths_d_file_under_a_staged_root_without_a_home_directory() {
+    // Create the staging directory, without any home directory
+    let host_home = assert_fs::TempDir::new().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let file = root.child("etc/paths.d/tool");
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(host_home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::paths_d("tool"))
+                .root(root.path());
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/bin", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Replaced),
+                replace_in_path("/opt/tool/bin", "/opt/tool/sbin", &options)
+            );
+            assert_eq!(
+                vec![Location::ConfigFile {
+                    path: file.path().to_path_buf(),
+                    line: 1
+                }],
+                find_in_path("/opt/tool/sbin", &options).unwrap().locations
+            );
+            assert_eq!(
+                "/opt/tool/sbin\n",
+                std::fs::read_to_string(file.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_updates_the_system_wide_profile_scripts_under_a_staged_root_without_a_home_directory() {
+    // Create the staging directory, without any home directory
+    let host_home = assert_fs::TempDir::new().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let profile = root.child("etc/profile.d/tool.sh");
+    let fish = root.child("usr/share/fish/vendor_conf.d/tool.fish");
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(host_home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::profile_d("tool"))
+                .root(root.path());
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/bin", Some("Tool"), &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Success),
+                set_env_var("TOOL_HOME", "/opt/tool", None, &options)
+            );
+            assert_eq!(
+                "# Tool\nexport PATH=\"/opt/tool/bin:$PATH\"\nexport TOOL_HOME=\"/opt/tool\"",
+                std::fs::read_to_string(profile.path()).unwrap()
+            );
+            assert_eq!(
+                "# Tool\nset -gx PATH \"/opt/tool/bin\" $PATH\nset -gx TOOL_HOME \"/opt/tool\"",
+                std::fs::read_to_string(fish.path()).unwrap()
+            );
+        },
+    );
+}
+
+// --- Skeleton directory ---
+
+#[test]
+fn it_adds_a_path_to_the_shell_configuration_files_of_a_skeleton_directory() {
+    // Create the skeleton directory with an existing .bashrc file
+    let home = assert_fs::TempDir::new().unwrap();
+    let skel = assert_fs::TempDir::new().unwrap();
+    let bashrc = skel.child(".bashrc");
+    bashrc.write_str("# ~/.bashrc\nalias ll='ls -l'\n").unwrap();
+    let zshrc = skel.child(".zshrc");
+    let fish_config = skel.child(".config/fish/config.fish");
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().target(Target::skeleton(skel.path()));
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("~/.tool/bin", Some("Tool"), &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options("~/.tool/bin", Some("Tool"), &options)
+            );
+            assert_eq!(
+                "# ~/.bashrc\nalias ll='ls -l'\n\n# Tool\nexport PATH=\"$HOME/.tool/bin:$PATH\"",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+            assert_eq!(
+                "# Tool\nexport PATH=\"$HOME/.tool/bin:$PATH\"",
+                std::fs::read_to_string(zshrc.path()).unwrap()
+            );
+            assert_eq!(
+                "# Tool\nset -gx PATH \"$HOME/.tool/bin\" $PATH",
+                std::fs::read_to_string(fish_config.path()).unwrap()
+            );
+            assert!(!home.child(".bashrc").exists());
+        },
+    );
+}
+
+#[test]
+fn it_removes_a_path_from_the_shell_configuration_files_of_a_skeleton_directory() {
+    // Create the skeleton directory under a staged root, with the path added
+    // to some of the configuration files
+    let home = assert_fs::TempDir::new().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    let bashrc = root.child("etc/skel/.bashrc");
+    bashrc
+        .write_str("alias ll='ls -l'\n\n# Tool\nexport PATH=\"/opt/tool/bin:$PATH\"\n")
+        .unwrap();
+    let zshrc = root.child("etc/skel/.zshrc");
+    zshrc
+        .write_str("# Tool\nexport PATH=\"/opt/tool/bin:$PATH\"\n")
+        .unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .target(Target::skeleton("/etc/skel"))
+                .root(root.path());
+
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/opt/tool/bin", Some("Tool"), &options)
+            );
+            assert_eq!(
+                "alias ll='ls -l'\n",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+            assert_eq!("", std::fs::read_to_string(zshrc.path()).unwrap());
+            assert!(!root.child("etc/skel/.config/fish/config.fish").exists());
+            assert_eq!(
+                Ok(UpdateType::NotInPath),
+                remove_from_path("/opt/tool/bin", None, &options)
+            );
+        },
+    );
+}
+
+// --- Another user ---
+
+#[test]
+fn it_updates_the_shell_configuration_file_of_another_user() {
+    // Create a system with the passwd database and the configuration file of
+    // the other user, and a separate home directory for the current user
+    let host_home = assert_fs::TempDir::new().unwrap();
+    let host_bashrc = host_home.child(".bashrc");
+    host_bashrc.touch().unwrap();
+    let root = assert_fs::TempDir::new().unwrap();
+    root.child("etc/passwd")
+        .write_str(
+            "root:x:0:0:root:/root:/bin/bash\n\
+             alice:x:1000:1000:Alice:/home/alice:/usr/bin/zsh\n",
+        )
+        .unwrap();
+    let zshrc = root.child("home/alice/.zshrc");
+    zshrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(host_home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new()
+                .root(root.path())
+                .user(User::name("alice"));
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("~/.tool/bin", Some("Tool"), &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::AlreadyInPath),
+                prepend_to_path_with_options("/home/alice/.tool/bin", None, &options)
+            );
+            assert_eq!(
+                "\n# Tool\nexport PATH=\"$HOME/.tool/bin:$PATH\"",
+                std::fs::read_to_string(zshrc.path()).unwrap()
+            );
+            assert_eq!("", std::fs::read_to_string(host_bashrc.path()).unwrap());
+
+            // The same user can be given by UID
+            let options = UpdateOptions::new().root(root.path()).user(User::Uid(1000));
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("~/.tool/bin", None, &options)
+            );
+        },
+    );
+}
+
+#[test]
+fn it_updates_the_shell_configuration_file_of_the_invoking_user() {
+    let root = assert_fs::TempDir::new().unwrap();
+    root.child("etc/passwd")
+        .write_str(
+            "root:x:0:0:root:/root:/bin/bash\n\
+             alice:x:1000:1000:Alice:/home/alice:/bin/bash\n\
+             bob:x:1001:1001:Bob:/home/bob:/usr/bin/fish\n",
+        )
+        .unwrap();
+    let alice_bashrc = root.child("home/alice/.bashrc");
+    alice_bashrc.touch().unwrap();
+    let bob_config = root.child("home/bob/.config/fish/config.fish");
+    bob_config.touch().unwrap();
+
+    let options = UpdateOptions::new().root(root.path()).user(User::Invoking);
+
+    // sudo sets the name of the invoking user
+    temp_env::with_vars([("SUDO_USER", Some("alice")), ("PKEXEC_UID", None)], || {
+        assert_eq!(
+            Ok(UpdateType::Success),
+            prepend_to_path_with_options("/opt/tool/bin", None, &options)
+        );
+    });
+
+    // pkexec sets the UID of the invoking user
+    temp_env::with_vars([("SUDO_USER", None), ("PKEXEC_UID", Some("1001"))], || {
+        assert_eq!(
+            Ok(UpdateType::Success),
+            prepend_to_path_with_options("/opt/tool/bin", None, &options)
+        );
+    });
+
+    assert_eq!(
+        "\nexport PATH=\"/opt/tool/bin:$PATH\"",
+        std::fs::read_to_string(alice_bashrc.path()).unwrap()
+    );
+    assert_eq!(
+        "\nset -gx PATH \"/opt/tool/bin\" $PATH",
+        std::fs::read_to_string(bob_config.path()).unwrap()
+    );
+}
+
+#[test]
+fn it_updates_the_shell_configuration_file_of_the_current_user_when_not_invoked_by_another_user() {
+    let home = assert_fs::TempDir::new().unwrap();
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+            ("SUDO_USER", None),
+            ("PKEXEC_UID", None),
+        ],
+        || {
+            let options = UpdateOptions::new().user(User::Invoking);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/bin", None, &options)
+            );
+            assert_eq!(
+                "\nexport PATH=\"/opt/tool/bin:$PATH\"",
+                std::fs::read_to_string(bashrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_returns_an_error_when_the_user_is_unknown() {
+    let root = assert_fs::TempDir::new().unwrap();
+    root.child("etc/passwd")
+        .write_str("root:x:0:0:root:/root:/bin/bash\n")
+        .unwrap();
+
+    let options = UpdateOptions::new()
+        .root(root.path())
+        .user(User::name("alice"));
+
+    assert_eq!(
+        Err(UnknownUser("alice".to_string())),
+        prepend_to_path_with_options("/opt/tool/bin", None, &options)
+    );
+}
+
+#[test]
+fn it_looks_up_the_other_user_in_the_user_database_of_the_system() {
+    // The nobody user exists on every system, and has no configuration file
+    let options = UpdateOptions::new()
+        .user(User::name("nobody"))
+        .shell("bash");
+
+    assert_eq!(
+        Vec::<Location>::new(),
+        find_in_path("/opt/tool/bin", &options).unwrap().locations
+    );
+    assert_eq!(
+        Err(UnknownUser("pathman-unknown-user".to_string())),
+        find_in_path(
+            "/opt/tool/bin",
+            &options.clone().user(User::name("pathman-unknown-user"))
+        )
+        .map(|status| status.locations)
+    );
+}
+
+#[test]
+fn it_gives_the_created_files_to_the_other_user() {
+    // Use the UID and GID of the current process, which can always be given
+    // to a file
+    let root = assert_fs::TempDir::new().unwrap();
+    let metadata = std::fs::metadata(root.path()).unwrap();
+    let (uid, gid) = (metadata.uid(), metadata.gid());
+    root.child("etc/passwd")
+        .write_str(&format!(
+            "alice:x:{uid}:{gid}:Alice:/home/alice:/bin/bash\n"
+        ))
+        .unwrap();
+
+    temp_env::with_var("XDG_CONFIG_HOME", None::<&str>, || {
+        let options = UpdateOptions::new()
+            .root(root.path())
+            .user(User::name("alice"))
+            .target(Target::environment_d("50-tool"));
+
+        assert_eq!(
+            Ok(UpdateType::Success),
+            prepend_to_path_with_options("/opt/tool/bin", None, &options)
+        );
+    });
+
+    for path in [
+        "home/alice/.config",
+        "home/alice/.config/environment.d",
+        "home/alice/.config/environment.d/50-tool.conf",
+    ] {
+        let metadata = std::fs::metadata(root.child(path).path()).unwrap();
+        assert_eq!((uid, gid), (metadata.uid(), metadata.gid()));
+    }
+}
+
+// --- Atomic writes ---
+
+/// The files written with the options
+type Writes = Arc<Mutex<Vec<WrittenFile>>>;
+
+/// Returns options recording the files written into the given list
+fn recording_options(writes: &Writes) -> UpdateOptions {
+    let writes = writes.clone();
+
+    UpdateOptions::new().on_write(move |file| writes.lock().unwrap().push(file.clone()))
+}
+
+#[test]
+fn it_replaces_the_configuration_file_atomically_keeping_its_permissions() {
+    // Create .bashrc file with restricted permissions
+    let home = assert_fs::TempDir::new().unwrap();
+    let bashrc = home.child(".bashrc");
+    bashrc.write_str("alias ll='ls -l'\n").unwrap();
+    set_permissions(&bashrc, Permissions::from_mode(0o640)).unwrap();
+    let inode = std::fs::metadata(bashrc.path()).unwrap().ino();
+
+    let writes = Writes::default();
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = recording_options(&writes);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/bin", None, &options)
+            );
+        },
+    );
+
+    let metadata = std::fs::metadata(bashrc.path()).unwrap();
+    assert_eq!(
+        "alias ll='ls -l'\n\nexport PATH=\"/opt/tool/bin:$PATH\"",
+        std::fs::read_to_string(bashrc.path()).unwrap()
+    );
+    assert_eq!(0o640, metadata.permissions().mode() & 0o777);
+    assert_ne!(inode, metadata.ino());
+    assert_eq!(
+        vec![WrittenFile {
+            path: bashrc.path().to_path_buf(),
+            target: bashrc.path().to_path_buf(),
+            strategy: WriteStrategy::Atomic,
+        }],
+        *writes.lock().unwrap()
+    );
+
+    // No temporary file is left behind
+    assert_eq!(1, std::fs::read_dir(home.path()).unwrap().count());
+}
+
+#[test]
+fn it_writes_the_configuration_file_in_place_when_it_cannot_be_replaced() {
+    // Create .bashrc file in a home directory that cannot be written to, so
+    // that the file cannot be replaced
+    let home = assert_fs::TempDir::new().unwrap();
+    let bashrc = home.child(".bashrc");
+    bashrc.touch().unwrap();
+    set_permissions(&home, Permissions::from_mode(0o555)).unwrap();
+    let inode = std::fs::metadata(bashrc.path()).unwrap().ino();
+
+    let writes = Writes::default();
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/bash".to_string())),
+        ],
+        || {
+            let options = recording_options(&writes);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/bin", None, &options)
+            );
+        },
+    );
+
+    set_permissions(&home, Permissions::from_mode(0o755)).unwrap();
+
+    assert_eq!(
+        "\nexport PATH=\"/opt/tool/bin:$PATH\"",
+        std::fs::read_to_string(bashrc.path()).unwrap()
+    );
+    assert_eq!(inode, std::fs::metadata(bashrc.path()).unwrap().ino());
+    assert_eq!(
+        vec![WrittenFile {
+            path: bashrc.path().to_path_buf(),
+            target: bashrc.path().to_path_buf(),
+            strategy: WriteStrategy::InPlace,
+        }],
+        *writes.lock().unwrap()
+    );
+}
+
+// --- Symbolic links ---
+
+#[test]
+fn it_edits_the_target_of_a_symlinked_configuration_file() {
+    // Create .zshrc file as a relative symbolic link into a dotfiles
+    // repository, as GNU Stow does
+    let home = assert_fs::TempDir::new().unwrap();
+    let target = home.child("dotfiles/zsh/.zshrc");
+    target.write_str("alias ll='ls -l'\n").unwrap();
+    let zshrc = home.child(".zshrc");
+    zshrc.symlink_to_file("dotfiles/zsh/.zshrc").unwrap();
+
+    let writes = Writes::default();
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            let options = recording_options(&writes);
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/bin", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Removed),
+                remove_from_path("/opt/tool/bin", None, &options)
+            );
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/bin", None, &options)
+            );
+        },
+    );
+
+    assert!(
+        std::fs::symlink_metadata(zshrc.path())
+            .unwrap()
+            .file_type()
+            .is_symlink()
+    );
+    assert_eq!(
+        "alias ll='ls -l'\n\nexport PATH=\"/opt/tool/bin:$PATH\"",
+        std::fs::read_to_string(target.path()).unwrap()
+    );
+
+    // The resolved path is reported along with the link
+    let written = WrittenFile {
+        path: zshrc.path().to_path_buf(),
+        target: std::fs::canonicalize(target.path()).unwrap(),
+        strategy: WriteStrategy::Atomic,
+    };
+    assert_eq!(vec![written; 3], *writes.lock().unwrap());
+}
+
+// --- Managed configuration files ---
+
+#[test]
+fn it_does_not_update_a_configuration_file_managed_by_home_manager() {
+    // Create .zshrc file as a symbolic link into the Nix store of a staged
+    // root, as home-manager does
+    let root = assert_fs::TempDir::new().unwrap();
+    let store_file = root.child("nix/store/0abc-home-manager-files/.zshrc");
+    store_file.write_str("alias ll='ls -l'\n").unwrap();
+    root.child("home/alice").create_dir_all().unwrap();
+    let zshrc = root.child("home/alice/.zshrc");
+    zshrc.symlink_to_file(store_file.path()).unwrap();
+
+    let options = UpdateOptions::new()
+        .root(root.path())
+        .home("/home/alice")
+        .shell("zsh");
+
+    assert_eq!(
+        Err(ManagedConfigFile(
+            zshrc.path().to_string_lossy().to_string()
+        )),
+        prepend_to_path_with_options("~/.tool/bin", Some("Tool"), &options)
+    );
+    assert_eq!(
+        Err(ManagedConfigFile(
+            zshrc.path().to_string_lossy().to_string()
+        )),
+        set_env_var("TOOL_HOME", "~/.tool", None, &options)
+    );
+    assert_eq!(
+        "alias ll='ls -l'\n",
+        std::fs::read_to_string(store_file.path()).unwrap()
+    );
+
+    // The lines can be added to the Nix configuration instead
+    assert_eq!(
+        Ok("# Tool\nexport PATH=\"$HOME/.tool/bin:$PATH\"".to_string()),
+        path_snippet(&["~/.tool/bin"], &Position::Front, Some("Tool"), &options)
+    );
+}
+
+#[test]
+fn it_updates_the_managed_fallback_when_the_configuration_file_is_managed() {
+    let root = assert_fs::TempDir::new().unwrap();
+    let store_file = root.child("nix/store/0abc-home-manager-files/.zshrc");
+    store_file.touch().unwrap();
+    root.child("home/alice").create_dir_all().unwrap();
+    let zshrc = root.child("home/alice/.zshrc");
+    zshrc.symlink_to_file(store_file.path()).unwrap();
+    let environment_d = root.child("home/alice/.config/environment.d/50-tool.conf");
+
+    let options = UpdateOptions::new()
+        .root(root.path())
+        .home("/home/alice")
+        .shell("zsh")
+        .managed_fallback(Target::environment_d("50-tool"));
+
+    assert_eq!(
+        Ok(UpdateType::Success),
+        prepend_to_path_with_options("/opt/tool/bin", None, &options)
+    );
+    assert_eq!(
+        Ok(UpdateType::AlreadyInPath),
+        prepend_to_path_with_options("/opt/tool/bin", None, &options)
+    );
+    assert_eq!(
+        "PATH=/opt/tool/bin:${PATH}",
+        std::fs::read_to_string(environment_d.path()).unwrap()
+    );
+    assert_eq!("", std::fs::read_to_string(store_file.path()).unwrap());
+}
+
+#[test]
+fn it_returns_the_lines_adding_paths_to_the_path() {
+    let options = UpdateOptions::new()
+        .root("/tmp/image")
+        .home("/home/alice")
+        .shell("fish")
+        .only_if_exists(true);
+
+    assert_eq!(
+        Ok("test -d \"/opt/a\"; and set -gx PATH \"/opt/a\" $PATH\n\
+            test -d \"/opt/b\"; and set -gx PATH \"/opt/b\" $PATH"
+            .to_string()),
+        path_snippet(&["/opt/b", "/opt/a"], &Position::Front, None, &options)
+    );
+}
+
+// --- Backups ---
+
+/// Returns the backups of the file with the given name in the given
+/// directory
+fn backups(dir: &Path, name: &str) -> Vec<PathBuf> {
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with(&format!("{name}.pathman-backup-"))
+        })
+        .collect();
+    backups.sort();
+
+    backups
+}
+
+#[test]
+fn it_backs_up_the_configuration_file_next_to_it_and_restores_it() {
+    // Create .zshrc file with restricted permissions
+    let home = assert_fs::TempDir::new().unwrap();
+    let zshrc = home.child(".zshrc");
+    zshrc.write_str("alias ll='ls -l'\n").unwrap();
+    set_permissions(&zshrc, Permissions::from_mode(0o600)).unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().backup(Backup::new().next_to_file(true));
+
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path_with_options("/opt/tool/bin", None, &options)
+            );
+
+            // The backup holds the content before the update
+            let backups = backups(home.path(), ".zshrc");
+            assert_eq!(1, backups.len());
+            assert_eq!(
+                "alias ll='ls -l'\n",
+                std::fs::read_to_string(&backups[0]).unwrap()
+            );
+            assert_eq!(
+                0o600,
+                std::fs::metadata(&backups[0]).unwrap().permissions().mode() & 0o777
+            );
+
+            assert_eq!(Ok(()), restore_backup(zshrc.path(), &options));
+            assert_eq!(
+                "alias ll='ls -l'\n",
+                std::fs::read_to_string(zshrc.path()).unwrap()
+            );
+            assert!(!backups[0].exists());
+
+            assert_eq!(
+                Err(NoBackup(zshrc.path().to_string_lossy().to_string())),
+                restore_backup(zshrc.path(), &options)
+            );
+        },
+    );
+}
+
+#[test]
+fn it_backs_up_the_files_of_a_paths_d_directory_outside_of_it() {
+    // Create the root directory with an existing paths.d file
+    let root = assert_fs::TempDir::new().unwrap();
+    let paths_d = root.child("etc/paths.d");
+    let file = paths_d.child("tool");
+    file.write_str("/opt/tool/bin\n").unwrap();
+
+    let options = UpdateOptions::new()
+        .target(Target::paths_d("tool"))
+        .root(root.path())
+        .backup(Backup::new().next_to_file(true));
+
+    assert_eq!(
+        Ok(UpdateType::Success),
+        append_to_path_with_options("/opt/tool/sbin", None, &options)
+    );
+
+    // path_helper reads every file of the directory, so the backup is stored
+    // in a sibling directory
+    assert_eq!(1, std::fs::read_dir(paths_d.path()).unwrap().count());
+    let backups = backups(&root.path().join("etc/paths.d.pathman-backups"), "tool");
+    assert_eq!(1, backups.len());
+    assert_eq!(
+        "/opt/tool/bin\n",
+        std::fs::read_to_string(&backups[0]).unwrap()
+    );
+
+    assert_eq!(Ok(()), restore_backup(file.path(), &options));
+    assert_eq!(
+        "/opt/tool/bin\n",
+        std::fs::read_to_string(file.path()).unwrap()
+    );
+}
+
+#[test]
+fn it_backs_up_the_files_of_a_skeleton_directory_outside_of_it() {
+    // Create the skeleton directory under a staged root, with an existing
+    // fish configuration file in a subdirectory
+    let root = assert_fs::TempDir::new().unwrap();
+    let skel = root.child("etc/skel");
+    let fish_config = skel.child(".config/fish/config.fish");
+    fish_config.write_str("alias ll 'ls -l'\n").unwrap();
+
+    let options = UpdateOptions::new()
+        .target(Target::skeleton("/etc/skel"))
+        .root(root.path())
+        .backup(Backup::new().next_to_file(true));
+
+    assert_eq!(
+        Ok(UpdateType::Success),
+        prepend_to_path_with_options("/opt/tool/bin", None, &options)
+    );
+
+    // The skeleton directory is copied to the home directory of new users,
+    // so the backups are stored next to it rather than next to the file
+    let fish_dir = fish_config.path().parent().unwrap();
+    assert_eq!(1, std::fs::read_dir(fish_dir).unwrap().count());
+    assert_eq!(3, std::fs::read_dir(skel.path()).unwrap().count());
+    let backups = backups(
+        &root.path().join("etc/skel.pathman-backups/.config/fish"),
+        "config.fish",
+    );
+    assert_eq!(1, backups.len());
+    assert_eq!(
+        "alias ll 'ls -l'\n",
+        std::fs::read_to_string(&backups[0]).unwrap()
+    );
+
+    assert_eq!(Ok(()), restore_backup(fish_config.path(), &options));
+    assert_eq!(
+        "alias ll 'ls -l'\n",
+        std::fs::read_to_string(fish_config.path()).unwrap()
+    );
+}
+
+#[test]
+fn it_records_the_absence_of_a_new_file_and_deletes_it_when_restoring() {
+    let root = assert_fs::TempDir::new().unwrap();
+    let file = root.child("etc/paths.d/tool");
+
+    let options = UpdateOptions::new()
+        .target(Target::paths_d("tool"))
+        .root(root.path())
+        .backup(Backup::new().next_to_file(true));
+
+    assert_eq!(
+        Ok(UpdateType::Success),
+        append_to_path_with_options("/opt/tool/bin", None, &options)
+    );
+    assert!(file.path().exists());
+
+    // The backup records that the file didn't exist before the update
+    let dir = root.path().join("etc/paths.d.pathman-backups");
+    let backups = backups(&dir, "tool");
+    assert_eq!(1, backups.len());
+    assert!(backups[0].to_string_lossy().ends_with(".absent"));
+
+    assert_eq!(Ok(()), restore_backup(file.path(), &options));
+    assert!(!file.path().exists());
+    assert!(!backups[0].exists());
+
+    assert_eq!(
+        Err(NoBackup(file.path().to_string_lossy().to_string())),
+        restore_backup(file.path(), &options)
+    );
+}
+
+#[test]
+fn it_keeps_the_last_backups_in_the_state_directory() {
+    let home = assert_fs::TempDir::new().unwrap();
+    let zshrc = home.child(".zshrc");
+    zshrc.write_str("alias ll='ls -l'").unwrap();
+    let state = assert_fs::TempDir::new().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+            (
+                "XDG_STATE_HOME",
+                Some(state.path().to_string_lossy().to_string()),
+            ),
+        ],
+        || {
+            let options = UpdateOptions::new().backup(Backup::new().keep(2));
+
+            for path in ["/opt/a", "/opt/b", "/opt/c"] {
+                assert_eq!(
+                    Ok(UpdateType::Success),
+                    prepend_to_path_with_options(path, None, &options)
+                );
+            }
+
+            // The backups mirror the location of the file
+            let dir = state
+                .path()
+                .join("pathman/backups")
+                .join(home.path().strip_prefix("/").unwrap());
+            assert_eq!(2, backups(&dir, ".zshrc").len());
+            assert!(backups(home.path(), ".zshrc").is_empty());
+
+            assert_eq!(Ok(()), restore_backup(zshrc.path(), &options));
+            assert_eq!(
+                "alias ll='ls -l'\nexport PATH=\"/opt/a:$PATH\"\nexport PATH=\"/opt/b:$PATH\"",
+                std::fs::read_to_string(zshrc.path()).unwrap()
+            );
+            assert_eq!(Ok(()), restore_backup(zshrc.path(), &options));
+            assert_eq!(
+                "alias ll='ls -l'\nexport PATH=\"/opt/a:$PATH\"",
+                std::fs::read_to_string(zshrc.path()).unwrap()
+            );
+            assert_eq!(
+                Err(NoBackup(zshrc.path().to_string_lossy().to_string())),
+                restore_backup(zshrc.path(), &options)
+            );
+        },
+    );
+}
+
+#[test]
+fn it_keeps_at_least_the_last_backup() {
+    let home = assert_fs::TempDir::new().unwrap();
+    let zshrc = home.child(".zshrc");
+    zshrc.write_str("alias ll='ls -l'").unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            let options = UpdateOptions::new().backup(Backup::new().next_to_file(true).keep(0));
+
+            for path in ["/opt/a", "/opt/b"] {
+                assert_eq!(
+                    Ok(UpdateType::Success),
+                    prepend_to_path_with_options(path, None, &options)
+                );
+            }
+            assert_eq!(1, backups(home.path(), ".zshrc").len());
+
+            assert_eq!(Ok(()), restore_backup(zshrc.path(), &options));
+            assert_eq!(
+                "alias ll='ls -l'\nexport PATH=\"/opt/a:$PATH\"",
+                std::fs::read_to_string(zshrc.path()).unwrap()
+            );
+        },
+    );
+}
+
+#[test]
+fn it_does_not_back_up_the_configuration_file_by_default() {
+    let home = assert_fs::TempDir::new().unwrap();
+    let zshrc = home.child(".zshrc");
+    zshrc.touch().unwrap();
+
+    temp_env::with_vars(
+        [
+            ("HOME", Some(home.path().to_string_lossy().to_string())),
+            ("SHELL", Some("/bin/zsh".to_string())),
+        ],
+        || {
+            assert_eq!(
+                Ok(UpdateType::Success),
+                prepend_to_path(PathBuf::from("/opt/tool/bin"), None)
+            );
+        },
+    );
+
+    assert_eq!(1, std::fs::read_dir(home.path()).unwrap().count());
+}
@@ -1,2 +1,278 @@
 #![cfg(windows)]
 
+use pathman::{
+    Activation, Backup, Expansion, Fallback, Position, UpdateOptions, UpdateType,
+    activation_commands, add_to_path, append_to_path_with_options, replace_in_path, restore_backup,
+    set_env_var,
+};
+use winreg::RegKey;
+use winreg::enums::{HKEY_CURRENT_USER, REG_EXPAND_SZ, REG_SZ};
+
+/// A scratch registry key under `HKEY_CURRENT_USER`, standing in for the
+/// `Environment` key of the user, which is deleted when dropped
+struct ScratchKey {
+    path: String,
+    key: RegKey,
+}
+
+impl ScratchKey {
+    /// Creates an empty scratch key with the given name, so that tests
+    /// running in parallel don't share their variables
+    fn new(name: &str) -> Self {
+        let path = format!(r"Software\pathman-tests\{name}");
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+
+        let _ = hkcu.delete_subkey_all(&path);
+        let (key, _) = hkcu.create_subkey(&path).unwrap();
+
+        Self { path, key }
+    }
+
+    /// Returns the options updating the variables of the scratch key
+    fn options(&self) -> UpdateOptions {
+        UpdateOptions::new().registry_key(&self.path)
+    }
+
+    /// Sets the given variable as a regular string
+    fn set(&self, name: &str, value: &str) {
+        self.key.set_value(name, &value).unwrap();
+    }
+
+    /// Returns the value of the given variable, if it is set
+    fn get(&self, name: &str) -> Option<String> {
+        self.key.get_value(name).ok()
+    }
+}
+
+impl Drop for ScratchKey {
+    fn drop(&mut self) {
+        let _ = RegKey::predef(HKEY_CURRENT_USER).delete_subkey_all(&self.path);
+    }
+}
+
+// --- Position ---
+
+#[test]
+fn it_inserts_the_path_before_the_anchor_entry() {
+    let key = ScratchKey::new("insert-before");
+    key.set("Path", r"C:\Users\alice\.local\bin;C:\Windows\system32");
+
+    assert_eq!(
+        Ok(UpdateType::Success),
+        add_to_path(
+            r"C:\tool\bin",
+            &Position::before(r"C:\Windows\system32", Fallback::Front),
+            None,
+            &key.options()
+        )
+    );
+    assert_eq!(
+        Some(r"C:\Users\alice\.local\bin;C:\tool\bin;C:\Windows\system32".to_string()),
+        key.get("Path")
+    );
+}
+
+#[test]
+fn it_inserts_the_path_after_the_anchor_entry() {
+    let key = ScratchKey::new("insert-after");
+    key.set("Path", r"C:\Users\alice\.local\bin;C:\Windows\system32");
+
+    assert_eq!(
+        Ok(UpdateType::Success),
+        add_to_path(
+            r"C:\tool\bin",
+            &Position::after(r"C:\Users\alice\.local\bin", Fallback::Back),
+            None,
+            &key.options()
+        )
+    );
+    assert_eq!(
+        Some(r"C:\Users\alice\.local\bin;C:\tool\bin;C:\Windows\system32".to_string()),
+        key.get("Path")
+    );
+}
+
+#[test]
+fn it_inserts_the_path_at_the_fallback_position_without_the_anchor_entry() {
+    let key = ScratchKey::new("insert-fallback");
+    key.set("Path", r"C:\Users\alice\.local\bin;C:\Windows\system32");
+
+    assert_eq!(
+        Ok(UpdateType::Success),
+        add_to_path(
+            r"C:\tool\bin",
+            &Position::before(r"C:\Program Files\Git\cmd", Fallback::Back),
+            None,
+            &key.options()
+        )
+    );
+    assert_eq!(
+        Some(r"C:\Users\alice\.local\bin;C:\Windows\system32;C:\tool\bin".to_string()),
+        key.get("Path")
+    );
+}
+
+// --- Replacement ---
+
+#[test]
+fn it_replaces_the_path_in_place() {
+    let key = ScratchKey::new("replace");
+    key.set(
+        "Path",
+        r"C:\Users\alice\.local\bin;C:\tool\1.0\bin;C:\Windows\system32",
+    );
+
+    assert_eq!(
+        Ok(UpdateType::Replaced),
+        replace_in_path(r"C:\tool\1.0\bin", r"C:\tool\2.0\bin", &key.options())
+    );
+    assert_eq!(
+        Some(r"C:\Users\alice\.local\bin;C:\tool\2.0\bin;C:\Windows\system32".to_string()),
+        key.get("Path")
+    );
+
+    // The old path being absent, the new path is prepended
+    assert_eq!(
+        Ok(UpdateType::Success),
+        replace_in_path(r"C:\other\1.0\bin", r"C:\other\2.0\bin", &key.options())
+    );
+    assert_eq!(
+        Some(
+            r"C:\other\2.0\bin;C:\Users\alice\.local\bin;C:\tool\2.0\bin;C:\Windows\system32"
+                .to_string()
+        ),
+        key.get("Path")
+    );
+}
+
+// --- Move ---
+
+#[test]
+fn it_moves_an_existing_path_to_the_requested_position() {
+    let key = ScratchKey::new("move");
+    key.set("Path", r"C:\tool\bin;C:\Windows\system32");
+
+    // The path is left where it is by default
+    assert_eq!(
+        Ok(UpdateType::AlreadyInPath),
+        append_to_path_with_options(r"C:\tool\bin", None, &key.options())
+    );
+
+    assert_eq!(
+        Ok(UpdateType::Moved),
+        append_to_path_with_options(r"C:\tool\bin", None, &key.options().move_existing(true))
+    );
+    assert_eq!(
+        Some(r"C:\Windows\system32;C:\tool\bin".to_string()),
+        key.get("Path")
+    );
+}
+
+// --- Activation ---
+
+#[test]
+fn it_returns_the_command_reloading_the_updated_variable() {
+    let key = ScratchKey::new("activation");
+
+    // The PATH combines the system-wide and the user's values
+    assert_eq!(
+        Ok(vec![Activation {
+            shell: "PowerShell".to_string(),
+            command:
+                "$env:Path = [Environment]::GetEnvironmentVariable('Path', 'Machine') + ';' + \
+                      [Environment]::GetEnvironmentVariable('Path', 'User')"
+                    .to_string(),
+        }]),
+        activation_commands(&UpdateOptions::new())
+    );
+
+    // Other variables are read from the key they are written to
+    assert_eq!(
+        Ok(vec![Activation {
+            shell: "PowerShell".to_string(),
+            command: format!(
+                "$env:PSModulePath = (Get-ItemProperty -Path 'HKCU:\\{}' -Name 'PSModulePath').'PSModulePath'",
+                key.path
+            ),
+        }]),
+        activation_commands(&key.options().variable("PSModulePath"))
+    );
+}
+
+// --- Expansion ---
+
+#[test]
+fn it_stores_paths_referencing_variables_as_expandable_strings() {
+    let key = ScratchKey::new("expand-sz");
+    key.set("Path", r"C:\Windows\system32");
+
+    let options = key.options().expansion(Expansion::Deferred);
+    assert_eq!(
+        Ok(UpdateType::Success),
+        append_to_path_with_options(r"%LOCALAPPDATA%\tool\bin", None, &options)
+    );
+
+    // Windows only expands the variables of REG_EXPAND_SZ values
+    let value = key.key.get_raw_value("Path").unwrap();
+    assert_eq!(REG_EXPAND_SZ, value.vtype);
+    assert_eq!(
+        Some(r"C:\Windows\system32;%LOCALAPPDATA%\tool\bin".to_string()),
+        key.get("Path")
+    );
+}
+
+// --- Other variables ---
+
+#[test]
+fn it_stores_literal_values_as_regular_strings() {
+    let key = ScratchKey::new("literal-sz");
+
+    // Windows would expand the variables of a REG_EXPAND_SZ value
+    let options = key.options().literal(true);
+    assert_eq!(
+        Ok(UpdateType::Success),
+        set_env_var("TOOL_HOME", r"%LOCALAPPDATA%\tool", None, &options)
+    );
+    assert_eq!(REG_SZ, key.key.get_raw_value("TOOL_HOME").unwrap().vtype);
+    assert_eq!(
+        Some(r"%LOCALAPPDATA%\tool".to_string()),
+        key.get("TOOL_HOME")
+    );
+}
+
+// --- Backups ---
+
+#[test]
+fn it_deletes_a_variable_that_was_not_set_when_restoring() {
+    let key = ScratchKey::new("restore-absent");
+    let backups = assert_fs::TempDir::new().unwrap();
+
+    let options = key.options().backup(Backup::new().dir(backups.path()));
+    assert_eq!(
+        Ok(UpdateType::Success),
+        set_env_var("TOOL_HOME", r"C:\tool", None, &options)
+    );
+    assert_eq!(Some(r"C:\tool".to_string()), key.get("TOOL_HOME"));
+
+    assert_eq!(Ok(()), restore_backup("TOOL_HOME", &options));
+    assert_eq!(None, key.get("TOOL_HOME"));
+}
+
+#[test]
+fn it_restores_a_variable_with_its_registry_type() {
+    let key = ScratchKey::new("restore-type");
+    let backups = assert_fs::TempDir::new().unwrap();
+
+    // A regular string whose `%` signs Windows doesn't expand
+    key.set("TOOL_HOME", r"C:\100%\tool");
+
+    let options = key.options().backup(Backup::new().dir(backups.path()));
+    assert_eq!(
+        Ok(UpdateType::Replaced),
+        set_env_var("TOOL_HOME", r"C:\tool", None, &options)
+    );
+
+    assert_eq!(Ok(()), restore_backup("TOOL_HOME", &options));
+    assert_eq!(Some(r"C:\100%\tool".to_string()), key.get("TOOL_HOME"));
+    assert_eq!(REG_SZ, key.key.get_raw_value("TOOL_HOME").unwrap().vtype);
+}